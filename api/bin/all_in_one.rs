@@ -0,0 +1,66 @@
+use std::net::SocketAddr;
+
+use eyre::Result;
+use tokio::net::TcpListener;
+use tokio_util::task::TaskTracker;
+use tracing::info;
+use yt_sub_api::{
+    config::{
+        middleware,
+        routes::app,
+        schedule::get_schedule,
+        shutdown::{shutdown_signal, wait_for_jobs},
+        ApiConfig,
+    },
+    storage::{self, init_db},
+};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    match run().await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            tracing::error!("{:?}", e);
+            Err(e)
+        }
+    }
+}
+
+// Runs the HTTP server and the scheduler in a single process sharing one database pool
+async fn run() -> Result<()> {
+    let config = ApiConfig::init()?;
+    middleware::init_logs(config, "all-in-one.log");
+
+    for secret in config.missing_secrets() {
+        tracing::warn!("{secret} is not configured, endpoints using it respond with 503");
+    }
+
+    init_db(None).await?;
+    let conn = storage::connect(None).await?;
+    let jobs = TaskTracker::new();
+
+    let mut sched = get_schedule(config, conn.clone(), jobs.clone()).await?;
+    let app = middleware::with_layers(app(conn).await);
+
+    if TcpListener::bind(config.bind_address).await.is_err() {
+        eyre::bail!("Address {} is already in use", config.bind_address);
+    }
+
+    let listener = tokio::net::TcpListener::bind(config.bind_address).await?;
+
+    sched.start().await?;
+
+    info!("Listening on {}", listener.local_addr().unwrap());
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+
+    // In-flight notifications are delivered before the process exits
+    wait_for_jobs(&jobs).await;
+    sched.shutdown().await?;
+
+    Ok(())
+}
@@ -1,6 +1,14 @@
 use eyre::Result;
-use std::time::Duration;
-use yt_sub_api::config::{middleware, schedule::get_schedule};
+use tokio_util::task::TaskTracker;
+use yt_sub_api::{
+    config::{
+        middleware,
+        schedule::get_schedule,
+        shutdown::{shutdown_signal, wait_for_jobs},
+        ApiConfig,
+    },
+    storage::{self, init_db},
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -14,10 +22,19 @@ async fn main() -> Result<()> {
 }
 
 async fn run() -> Result<()> {
-    middleware::init_logs("scheduler.log");
+    let config = ApiConfig::init()?;
+    middleware::init_logs(config, "scheduler.log");
 
-    let sched = get_schedule().await?;
+    init_db(None).await?;
+    let conn = storage::connect(None).await?;
+    let jobs = TaskTracker::new();
+
+    let mut sched = get_schedule(config, conn, jobs.clone()).await?;
     sched.start().await?;
-    tokio::time::sleep(Duration::MAX).await;
+
+    shutdown_signal().await;
+    wait_for_jobs(&jobs).await;
+    sched.shutdown().await?;
+
     Ok(())
 }
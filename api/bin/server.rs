@@ -1,14 +1,11 @@
-use axum::middleware::from_fn;
+use std::net::SocketAddr;
+
 use eyre::Result;
-use std::time::Duration;
 use tokio::net::TcpListener;
-use tower_http::{
-    catch_panic::CatchPanicLayer, compression::CompressionLayer, timeout::TimeoutLayer,
-};
 use tracing::info;
 use yt_sub_api::{
-    config::{middleware, routes::app},
-    lite_helpers::{init_lite_db, sqlite_conn},
+    config::{middleware, routes::app, shutdown::shutdown_signal, ApiConfig},
+    storage::{self, init_db},
 };
 
 #[tokio::main]
@@ -23,29 +20,30 @@ async fn main() -> Result<()> {
 }
 
 async fn run() -> Result<()> {
-    middleware::init_logs("server.log");
-
-    init_lite_db(None).await?;
-    let conn = sqlite_conn(None).await?;
-    let app = app(conn)
-        .await
-        .layer(middleware::logging())
-        .layer(from_fn(middleware::only_ssl))
-        .layer(TimeoutLayer::new(Duration::from_secs(10)))
-        .layer(CompressionLayer::new())
-        .layer(CatchPanicLayer::new())
-        .layer(from_fn(middleware::security_headers));
-
-    let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
-
-    if TcpListener::bind(format!("0.0.0.0:{port}")).await.is_err() {
-        eyre::bail!("Port {} is already in use", port);
+    let config = ApiConfig::init()?;
+    middleware::init_logs(config, "server.log");
+
+    for secret in config.missing_secrets() {
+        tracing::warn!("{secret} is not configured, endpoints using it respond with 503");
+    }
+
+    init_db(None).await?;
+    let conn = storage::connect(None).await?;
+    let app = middleware::with_layers(app(conn).await);
+
+    if TcpListener::bind(config.bind_address).await.is_err() {
+        eyre::bail!("Address {} is already in use", config.bind_address);
     }
 
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}")).await?;
+    let listener = tokio::net::TcpListener::bind(config.bind_address).await?;
 
     info!("Listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,11 @@
+-- Add down migration script here
+
+DROP TABLE IF EXISTS notifier_health;
+DROP TABLE IF EXISTS outbox;
+DROP TABLE IF EXISTS pending_videos;
+DROP TABLE IF EXISTS videos;
+DROP TABLE IF EXISTS schedules;
+DROP TABLE IF EXISTS notifiers;
+DROP TABLE IF EXISTS subscriptions;
+DROP TABLE IF EXISTS channels;
+DROP TABLE IF EXISTS users;
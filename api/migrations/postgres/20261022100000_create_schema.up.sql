@@ -0,0 +1,84 @@
+-- Add up migration script here
+
+CREATE TABLE users (
+    id TEXT NOT NULL PRIMARY KEY,
+    last_run_at TIMESTAMPTZ,
+    templates_json TEXT
+);
+
+CREATE TABLE channels (
+    id TEXT NOT NULL PRIMARY KEY,
+    handle TEXT NOT NULL,
+    description TEXT NOT NULL
+);
+
+CREATE TABLE subscriptions (
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    channel_id TEXT NOT NULL REFERENCES channels (id),
+    position BIGINT NOT NULL,
+    PRIMARY KEY (user_id, channel_id)
+);
+
+CREATE INDEX subscriptions_channel_id ON subscriptions (channel_id);
+
+CREATE TABLE notifiers (
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    notifier_index BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    config_json TEXT NOT NULL,
+    PRIMARY KEY (user_id, notifier_index)
+);
+
+CREATE TABLE schedules (
+    user_id TEXT NOT NULL PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    schedule_json TEXT NOT NULL
+);
+
+CREATE TABLE videos (
+    link TEXT NOT NULL PRIMARY KEY,
+    channel_id TEXT NOT NULL REFERENCES channels (id) ON DELETE CASCADE,
+    channel TEXT NOT NULL,
+    title TEXT NOT NULL,
+    thumbnail_url TEXT,
+    published_at TIMESTAMPTZ NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX videos_channel_id_published_at ON videos (channel_id, published_at);
+
+CREATE TABLE pending_videos (
+    user_id TEXT NOT NULL,
+    notifier_index BIGINT NOT NULL,
+    videos_json TEXT NOT NULL,
+    last_delivered_at TIMESTAMPTZ,
+    slack_thread_json TEXT,
+    PRIMARY KEY (user_id, notifier_index)
+);
+
+CREATE TABLE outbox (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    notifier_index BIGINT NOT NULL,
+    notifier_name TEXT NOT NULL,
+    videos_json TEXT NOT NULL,
+    message TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts BIGINT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    delivered_at TIMESTAMPTZ
+);
+
+CREATE INDEX outbox_status_next_attempt_at ON outbox (status, next_attempt_at);
+CREATE INDEX outbox_user_id ON outbox (user_id);
+
+CREATE TABLE notifier_health (
+    user_id TEXT NOT NULL,
+    notifier_index BIGINT NOT NULL,
+    consecutive_failures BIGINT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    last_failure_at TIMESTAMPTZ,
+    suspended_at TIMESTAMPTZ,
+    PRIMARY KEY (user_id, notifier_index)
+);
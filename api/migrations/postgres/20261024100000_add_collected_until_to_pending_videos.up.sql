@@ -0,0 +1,3 @@
+-- Add up migration script here
+
+ALTER TABLE pending_videos ADD COLUMN collected_until TIMESTAMPTZ;
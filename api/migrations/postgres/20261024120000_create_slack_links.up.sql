@@ -0,0 +1,17 @@
+-- Add up migration script here
+
+CREATE TABLE slack_links (
+    team_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (team_id, channel_id)
+);
+
+CREATE INDEX slack_links_user_id ON slack_links (user_id);
+
+CREATE TABLE slack_link_codes (
+    code TEXT NOT NULL PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL
+);
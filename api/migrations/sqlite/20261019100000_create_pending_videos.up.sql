@@ -0,0 +1,9 @@
+-- Add up migration script here
+
+CREATE TABLE pending_videos (
+    user_id TEXT NOT NULL,
+    notifier_index INTEGER NOT NULL,
+    videos_json TEXT NOT NULL,
+    last_delivered_at TIMESTAMP,
+    PRIMARY KEY (user_id, notifier_index)
+);
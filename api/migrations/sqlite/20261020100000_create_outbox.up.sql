@@ -0,0 +1,19 @@
+-- Add up migration script here
+
+CREATE TABLE outbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    notifier_index INTEGER NOT NULL,
+    notifier_name TEXT NOT NULL,
+    videos_json TEXT NOT NULL,
+    message TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX outbox_status_next_attempt_at ON outbox (status, next_attempt_at);
+CREATE INDEX outbox_user_id ON outbox (user_id);
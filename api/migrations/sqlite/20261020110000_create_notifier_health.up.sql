@@ -0,0 +1,11 @@
+-- Add up migration script here
+
+CREATE TABLE notifier_health (
+    user_id TEXT NOT NULL,
+    notifier_index INTEGER NOT NULL,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    last_failure_at TIMESTAMP,
+    suspended_at TIMESTAMP,
+    PRIMARY KEY (user_id, notifier_index)
+);
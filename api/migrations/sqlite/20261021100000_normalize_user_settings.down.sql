@@ -0,0 +1,27 @@
+-- Add down migration script here
+
+ALTER TABLE users ADD COLUMN settings_json TEXT NOT NULL DEFAULT '{}';
+
+UPDATE users SET settings_json = json_object(
+    'channels', (
+        SELECT json_group_array(json_object('handle', handle, 'description', description, 'channel_id', id))
+        FROM (
+            SELECT c.* FROM subscriptions s JOIN channels c ON c.id = s.channel_id
+            WHERE s.user_id = users.id ORDER BY s.position
+        )
+    ),
+    'notifiers', (
+        SELECT json_group_array(json(config_json))
+        FROM (SELECT config_json FROM notifiers WHERE user_id = users.id ORDER BY notifier_index)
+    ),
+    'api_key', NULL,
+    'schedule', (SELECT json(schedule_json) FROM schedules WHERE user_id = users.id),
+    'templates', json(COALESCE(templates_json, '{}'))
+);
+
+ALTER TABLE users DROP COLUMN templates_json;
+
+DROP TABLE IF EXISTS schedules;
+DROP TABLE IF EXISTS notifiers;
+DROP TABLE IF EXISTS subscriptions;
+DROP TABLE IF EXISTS channels;
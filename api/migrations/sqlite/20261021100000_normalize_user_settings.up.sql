@@ -0,0 +1,66 @@
+-- Add up migration script here
+
+CREATE TABLE channels (
+    id TEXT NOT NULL PRIMARY KEY,
+    handle TEXT NOT NULL,
+    description TEXT NOT NULL
+);
+
+CREATE TABLE subscriptions (
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    channel_id TEXT NOT NULL REFERENCES channels (id),
+    position INTEGER NOT NULL,
+    PRIMARY KEY (user_id, channel_id)
+);
+
+CREATE INDEX subscriptions_channel_id ON subscriptions (channel_id);
+
+CREATE TABLE notifiers (
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    notifier_index INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    config_json TEXT NOT NULL,
+    PRIMARY KEY (user_id, notifier_index)
+);
+
+CREATE TABLE schedules (
+    user_id TEXT NOT NULL PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    schedule_json TEXT NOT NULL
+);
+
+ALTER TABLE users ADD COLUMN templates_json TEXT;
+
+-- Move existing settings out of the JSON blob
+INSERT INTO channels (id, handle, description)
+SELECT
+    json_extract(c.value, '$.channel_id'),
+    json_extract(c.value, '$.handle'),
+    json_extract(c.value, '$.description')
+FROM users u, json_each(u.settings_json, '$.channels') c
+WHERE true
+ON CONFLICT (id) DO NOTHING;
+
+INSERT INTO subscriptions (user_id, channel_id, position)
+SELECT u.id, json_extract(c.value, '$.channel_id'), c.key
+FROM users u, json_each(u.settings_json, '$.channels') c
+WHERE true
+ON CONFLICT (user_id, channel_id) DO NOTHING;
+
+-- Notifiers are stored in their serde format, e.g. {"Slack": {...}} or "Telegram"
+INSERT INTO notifiers (user_id, notifier_index, kind, config_json)
+SELECT
+    u.id,
+    n.key,
+    CASE n.type WHEN 'object' THEN (SELECT key FROM json_each(n.value)) ELSE n.value END,
+    CASE n.type WHEN 'object' THEN n.value ELSE json_quote(n.value) END
+FROM users u, json_each(u.settings_json, '$.notifiers') n;
+
+INSERT INTO schedules (user_id, schedule_json)
+SELECT id, json_extract(settings_json, '$.schedule')
+FROM users
+WHERE json_type(settings_json, '$.schedule') != 'null';
+
+UPDATE users SET templates_json = json_extract(settings_json, '$.templates')
+WHERE json_type(settings_json, '$.templates') = 'object';
+
+ALTER TABLE users DROP COLUMN settings_json;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+
+CREATE TABLE videos (
+    link TEXT NOT NULL PRIMARY KEY,
+    channel_id TEXT NOT NULL REFERENCES channels (id) ON DELETE CASCADE,
+    channel TEXT NOT NULL,
+    title TEXT NOT NULL,
+    thumbnail_url TEXT,
+    published_at TIMESTAMP NOT NULL,
+    fetched_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX videos_channel_id_published_at ON videos (channel_id, published_at);
@@ -0,0 +1,8 @@
+-- Add up migration script here
+
+CREATE TABLE channel_handles (
+    handle TEXT NOT NULL PRIMARY KEY,
+    channel_id TEXT,
+    channel_name TEXT,
+    fetched_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,7 @@
+-- Add up migration script here
+
+CREATE TABLE channel_searches (
+    query TEXT NOT NULL PRIMARY KEY,
+    results_json TEXT NOT NULL,
+    fetched_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+
+ALTER TABLE pending_videos DROP COLUMN collected_until;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+
+ALTER TABLE outbox DROP COLUMN delivered_parts;
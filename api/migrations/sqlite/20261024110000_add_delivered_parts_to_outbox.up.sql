@@ -0,0 +1,3 @@
+-- Add up migration script here
+
+ALTER TABLE outbox ADD COLUMN delivered_parts INTEGER NOT NULL DEFAULT 0;
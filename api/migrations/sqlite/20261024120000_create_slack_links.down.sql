@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+DROP TABLE IF EXISTS slack_link_codes;
+DROP TABLE IF EXISTS slack_links;
@@ -0,0 +1,78 @@
+use axum::http::HeaderMap;
+use eyre::{OptionExt, Result};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::storage::Storage;
+
+pub fn generate_api_key() -> String {
+    Uuid::new_v4().to_string()
+}
+
+// Only API key hashes are stored, accounts are identified by them
+pub fn user_id(api_key: &str) -> String {
+    hex::encode(Sha256::digest(api_key.as_bytes()))
+}
+
+pub fn api_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(api_key) = headers.get("X-API-KEY") {
+        return api_key.to_str().ok();
+    }
+
+    headers
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+pub async fn authenticate(headers: &HeaderMap, conn: &dyn Storage) -> Result<String> {
+    let api_key = api_key(headers).ok_or_eyre("Missing X-API-KEY header")?;
+    let user_id = user_id(api_key.trim());
+
+    if !conn.user_exists(&user_id).await? {
+        eyre::bail!("Invalid API key!")
+    }
+
+    Ok(user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use crate::{
+        controllers::account::tests::build_settings, storage::tests::setup_test_db,
+        user_settings_api::UserSettingsAPI,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_authenticate() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+
+        let settings = build_settings(true, None);
+        let api_key = settings.api_key.clone().unwrap();
+        settings.save(&settings.user_id(), &*conn).await?;
+
+        let mut headers = HeaderMap::new();
+        assert!(authenticate(&headers, &*conn).await.is_err());
+
+        headers.insert("X-API-KEY", HeaderValue::from_str(&api_key)?);
+        assert_eq!(authenticate(&headers, &*conn).await?, user_id(&api_key));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            HeaderValue::from_str(&format!("Bearer {api_key}"))?,
+        );
+        assert_eq!(authenticate(&headers, &*conn).await?, user_id(&api_key));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-API-KEY", HeaderValue::from_static("invalid"));
+        assert!(authenticate(&headers, &*conn).await.is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,409 @@
+use std::{fmt::Display, net::SocketAddr, path::PathBuf, str::FromStr, sync::OnceLock};
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use chrono_tz::Tz;
+use eyre::{eyre, Result};
+use serde::Deserialize;
+
+static CONFIG: OnceLock<ApiConfig> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Env {
+    Development,
+    Production,
+    Test,
+}
+
+impl Env {
+    pub fn is_dev(&self) -> bool {
+        self == &Self::Development
+    }
+}
+
+impl FromStr for Env {
+    type Err = eyre::Report;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "development" => Ok(Self::Development),
+            "production" => Ok(Self::Production),
+            "test" => Ok(Self::Test),
+            _ => eyre::bail!("expected development, production or test"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Full,
+    Compact,
+    Pretty,
+}
+
+impl FromStr for LogFormat {
+    type Err = eyre::Report;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "full" => Ok(Self::Full),
+            "compact" => Ok(Self::Compact),
+            "pretty" => Ok(Self::Pretty),
+            _ => eyre::bail!("expected full, compact or pretty"),
+        }
+    }
+}
+
+/// API server and scheduler settings. Values are read from an optional TOML file
+/// (path in the `API_CONFIG` env var) and overridden by env vars.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    pub env: Env,
+    pub database_url: String,
+    pub bind_address: SocketAddr,
+    pub log_dir: PathBuf,
+    pub log_format: LogFormat,
+    // Used for log timestamps
+    pub timezone: String,
+    pub check_interval_minutes: u32,
+    pub max_channels: usize,
+    pub max_notifiers: usize,
+    pub uptime_url: Option<String>,
+    // Token bucket sizes, requests are refilled evenly over the period
+    pub ip_rate_limit_per_minute: u32,
+    pub key_rate_limit_per_minute: u32,
+    pub max_accounts_per_ip_per_day: u32,
+    // Endpoints that need a missing secret respond with 503
+    pub youtube_api_key: Option<String>,
+    pub slack_signing_secret: Option<String>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            env: Env::Production,
+            database_url: "sqlite://ytsub.db".to_string(),
+            bind_address: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            log_dir: PathBuf::from("./"),
+            log_format: LogFormat::Full,
+            timezone: "CET".to_string(),
+            check_interval_minutes: 60,
+            max_channels: 100,
+            max_notifiers: 5,
+            uptime_url: None,
+            ip_rate_limit_per_minute: 60,
+            key_rate_limit_per_minute: 60,
+            max_accounts_per_ip_per_day: 3,
+            youtube_api_key: None,
+            slack_signing_secret: None,
+        }
+    }
+}
+
+impl ApiConfig {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("API_CONFIG").ok().map(PathBuf::from);
+        Self::build(path, |key| std::env::var(key).ok())
+    }
+
+    /// Loads the config once at startup, later calls to `current` return it.
+    pub fn init() -> Result<&'static Self> {
+        let config = Self::load()?;
+        Ok(CONFIG.get_or_init(|| config))
+    }
+
+    // Defaults are used if the config was not initialized, e.g. in tests
+    pub fn current() -> &'static Self {
+        CONFIG.get_or_init(Self::default)
+    }
+
+    fn build(path: Option<PathBuf>, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut config = match path {
+            Some(path) => {
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| eyre!("Failed to read config file {}: {}", path.display(), e))?;
+                toml::from_str(&content)
+                    .map_err(|e| eyre!("Invalid config file {}: {}", path.display(), e))?
+            }
+            None => Self::default(),
+        };
+
+        if let Some(value) = env("ENV") {
+            config.env = parse_env("ENV", &value)?;
+        }
+        if let Some(value) = env("DATABASE_URL") {
+            config.database_url = value;
+        }
+        // PORT is supported for backwards compatibility, BIND_ADDRESS takes precedence
+        if let Some(value) = env("PORT") {
+            let port: u16 = parse_env("PORT", &value)?;
+            config.bind_address.set_port(port);
+        }
+        if let Some(value) = env("BIND_ADDRESS") {
+            config.bind_address = parse_env("BIND_ADDRESS", &value)?;
+        }
+        if let Some(value) = env("LOG_DIR") {
+            config.log_dir = PathBuf::from(value);
+        }
+        if let Some(value) = env("LOG_FORMAT") {
+            config.log_format = parse_env("LOG_FORMAT", &value)?;
+        }
+        if let Some(value) = env("TIMEZONE") {
+            config.timezone = value;
+        }
+        if let Some(value) = env("CHECK_INTERVAL_MINUTES") {
+            config.check_interval_minutes = parse_env("CHECK_INTERVAL_MINUTES", &value)?;
+        }
+        if let Some(value) = env("MAX_CHANNELS") {
+            config.max_channels = parse_env("MAX_CHANNELS", &value)?;
+        }
+        if let Some(value) = env("MAX_NOTIFIERS") {
+            config.max_notifiers = parse_env("MAX_NOTIFIERS", &value)?;
+        }
+        if let Some(value) = env("UPTIME_URL") {
+            config.uptime_url = Some(value);
+        }
+        if let Some(value) = env("IP_RATE_LIMIT_PER_MINUTE") {
+            config.ip_rate_limit_per_minute = parse_env("IP_RATE_LIMIT_PER_MINUTE", &value)?;
+        }
+        if let Some(value) = env("KEY_RATE_LIMIT_PER_MINUTE") {
+            config.key_rate_limit_per_minute = parse_env("KEY_RATE_LIMIT_PER_MINUTE", &value)?;
+        }
+        if let Some(value) = env("MAX_ACCOUNTS_PER_IP_PER_DAY") {
+            config.max_accounts_per_ip_per_day = parse_env("MAX_ACCOUNTS_PER_IP_PER_DAY", &value)?;
+        }
+        if let Some(value) = env("YOUTUBE_API_KEY") {
+            config.youtube_api_key = Some(value);
+        }
+        if let Some(value) = env("SLACK_SIGNING_SECRET") {
+            config.slack_signing_secret = Some(value);
+        }
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let db_schemes = ["sqlite:", "postgres://", "postgresql://"];
+        if !db_schemes
+            .iter()
+            .any(|scheme| self.database_url.starts_with(scheme))
+        {
+            eyre::bail!(
+                "Invalid database_url '{}', expected a sqlite:// or postgres:// URL",
+                self.database_url
+            );
+        }
+
+        if self.timezone.parse::<Tz>().is_err() {
+            eyre::bail!(
+                "Invalid timezone '{}', expected an IANA name, e.g. Europe/Warsaw",
+                self.timezone
+            );
+        }
+
+        // Checks are scheduled with cron, so the interval has to fit evenly into an hour or a day
+        let minutes = self.check_interval_minutes;
+        let valid_interval = minutes > 0
+            && (60 % minutes == 0 || (minutes.is_multiple_of(60) && 24 % (minutes / 60) == 0));
+        if !valid_interval {
+            eyre::bail!(
+                "Invalid check_interval_minutes {}, it must evenly divide an hour or a day",
+                minutes
+            );
+        }
+
+        if self.max_channels == 0 || self.max_notifiers == 0 {
+            eyre::bail!("max_channels and max_notifiers must be greater than 0");
+        }
+
+        if self.ip_rate_limit_per_minute == 0
+            || self.key_rate_limit_per_minute == 0
+            || self.max_accounts_per_ip_per_day == 0
+        {
+            eyre::bail!("Rate limits must be greater than 0");
+        }
+
+        for (name, secret) in [
+            ("youtube_api_key", &self.youtube_api_key),
+            ("slack_signing_secret", &self.slack_signing_secret),
+        ] {
+            if secret
+                .as_ref()
+                .is_some_and(|secret| secret.trim().is_empty())
+            {
+                eyre::bail!("{} must not be empty", name);
+            }
+        }
+
+        if let Some(url) = &self.uptime_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                eyre::bail!("Invalid uptime_url '{}', expected an HTTP URL", url);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn missing_secrets(&self) -> Vec<&'static str> {
+        let mut missing = vec![];
+        if self.youtube_api_key.is_none() {
+            missing.push("youtube_api_key");
+        }
+        if self.slack_signing_secret.is_none() {
+            missing.push("slack_signing_secret");
+        }
+        missing
+    }
+
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+
+    pub fn check_videos_cron(&self) -> String {
+        let minutes = self.check_interval_minutes;
+
+        if minutes.is_multiple_of(60) {
+            format!("0 0 */{} * * *", minutes / 60)
+        } else {
+            format!("0 */{} * * * *", minutes)
+        }
+    }
+
+    /// First scheduled video check at or after `at`. The interval evenly divides a day,
+    /// so the checks fall on its multiples since midnight UTC.
+    pub fn next_check_at(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let interval = Duration::minutes(self.check_interval_minutes as i64);
+        let slot = at
+            .duration_trunc(interval)
+            .expect("Failed to truncate date");
+
+        if slot == at {
+            at
+        } else {
+            slot + interval
+        }
+    }
+}
+
+fn parse_env<T: FromStr>(key: &str, value: &str) -> Result<T>
+where
+    T::Err: Display,
+{
+    value
+        .parse()
+        .map_err(|e| eyre!("Invalid {} env var '{}': {}", key, value, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn build(content: Option<&str>, env: &[(&str, &str)]) -> Result<ApiConfig> {
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        let path = content.map(|content| {
+            let path = PathBuf::from(format!("/tmp/{}-api.toml", Uuid::new_v4()));
+            fs::write(&path, content).expect("Failed to write config");
+            path
+        });
+
+        let config = ApiConfig::build(path.clone(), |key| env.get(key).cloned());
+
+        if let Some(path) = path {
+            let _ = fs::remove_file(path);
+        }
+
+        config
+    }
+
+    #[test]
+    fn test_defaults() -> Result<()> {
+        let config = build(None, &[])?;
+
+        assert_eq!(config, ApiConfig::default());
+        assert_eq!(config.check_videos_cron(), "0 0 */1 * * *");
+        assert_eq!(
+            config.next_check_at("2024-11-07T08:00:00Z".parse()?),
+            "2024-11-07T08:00:00Z".parse::<DateTime<Utc>>()?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_and_env() -> Result<()> {
+        let content = r#"
+            env = "development"
+            database_url = "postgres://localhost/ytsub"
+            bind_address = "127.0.0.1:8080"
+            log_format = "compact"
+            timezone = "Europe/Warsaw"
+            check_interval_minutes = 15
+            uptime_url = "https://uptime.example.com/ping"
+        "#;
+
+        let config = build(
+            Some(content),
+            &[
+                ("MAX_CHANNELS", "50"),
+                ("PORT", "9000"),
+                ("YOUTUBE_API_KEY", "key"),
+            ],
+        )?;
+
+        assert!(config.env.is_dev());
+        assert_eq!(config.database_url, "postgres://localhost/ytsub");
+        assert_eq!(config.bind_address.to_string(), "127.0.0.1:9000");
+        assert_eq!(config.log_format, LogFormat::Compact);
+        assert_eq!(config.tz(), Tz::Europe__Warsaw);
+        assert_eq!(config.check_videos_cron(), "0 */15 * * * *");
+        assert_eq!(
+            config.next_check_at("2024-11-07T08:16:30Z".parse()?),
+            "2024-11-07T08:30:00Z".parse::<DateTime<Utc>>()?
+        );
+        assert_eq!(config.max_channels, 50);
+        assert_eq!(config.max_notifiers, 5);
+        assert_eq!(config.max_accounts_per_ip_per_day, 3);
+        assert_eq!(config.youtube_api_key.as_deref(), Some("key"));
+        assert_eq!(config.missing_secrets(), vec!["slack_signing_secret"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let error = |content: Option<&str>, env: &[(&str, &str)]| {
+            build(content, env)
+                .expect_err("Config should be invalid")
+                .to_string()
+        };
+
+        assert_eq!(
+            error(None, &[("ENV", "staging")]),
+            "Invalid ENV env var 'staging': expected development, production or test"
+        );
+        assert!(error(None, &[("MAX_CHANNELS", "many")]).starts_with("Invalid MAX_CHANNELS"));
+        assert!(error(None, &[("DATABASE_URL", "mysql://db")]).starts_with("Invalid database_url"));
+        assert!(error(None, &[("TIMEZONE", "Mars/Base")]).starts_with("Invalid timezone"));
+        assert!(error(None, &[("CHECK_INTERVAL_MINUTES", "45")])
+            .starts_with("Invalid check_interval_minutes"));
+        assert!(error(None, &[("IP_RATE_LIMIT_PER_MINUTE", "0")])
+            .starts_with("Rate limits must be greater than 0"));
+        assert_eq!(
+            error(None, &[("SLACK_SIGNING_SECRET", " ")]),
+            "slack_signing_secret must not be empty"
+        );
+        assert!(error(Some("database = \"ytsub.db\""), &[]).starts_with("Invalid config file"));
+    }
+}
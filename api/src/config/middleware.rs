@@ -1,38 +1,28 @@
+use std::time::{Duration, Instant};
+
 use axum::{
-    extract::Request,
+    extract::{MatchedPath, Request},
     http::{HeaderValue, Uri},
-    middleware::Next,
+    middleware::{from_fn, Next},
     response::{IntoResponse, Response},
+    Router,
 };
 
 use reqwest::StatusCode;
-use time::UtcOffset;
 
-use tower_http::trace::{self, TraceLayer};
+use tower_http::{
+    catch_panic::CatchPanicLayer,
+    compression::CompressionLayer,
+    timeout::TimeoutLayer,
+    trace::{self, TraceLayer},
+};
 use tracing::Level;
-use tracing_subscriber::fmt::time::OffsetTime;
-
-#[derive(Debug, PartialEq)]
-enum Env {
-    Development,
-    Production,
-    Test,
-}
-
-impl Env {
-    fn current() -> Self {
-        match std::env::var("ENV").expect("ENV is not set").as_str() {
-            "development" => Self::Development,
-            "production" => Self::Production,
-            "test" => Self::Test,
-            _ => panic!("Invalid ENV"),
-        }
-    }
+use tracing_subscriber::fmt::{format::Writer, time::FormatTime};
 
-    fn is_dev(&self) -> bool {
-        self == &Self::Development
-    }
-}
+use crate::{
+    config::api_config::{ApiConfig, LogFormat},
+    metrics,
+};
 
 pub fn logging() -> tower_http::trace::TraceLayer<
     tower_http::classify::SharedClassifier<tower_http::classify::ServerErrorsAsFailures>,
@@ -42,6 +32,46 @@ pub fn logging() -> tower_http::trace::TraceLayer<
         .on_response(trace::DefaultOnResponse::new().level(Level::INFO))
 }
 
+// Production middleware stack shared by the server binaries
+pub fn with_layers(app: Router) -> Router {
+    app.layer(logging())
+        .layer(from_fn(only_ssl))
+        .layer(TimeoutLayer::new(Duration::from_secs(10)))
+        .layer(CompressionLayer::new())
+        .layer(CatchPanicLayer::new())
+        .layer(from_fn(security_headers))
+}
+
+// Must be added with `route_layer` for the matched route to be available
+pub async fn track_metrics(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unknown".to_string(), |path| path.as_str().to_string());
+
+    let response = next.run(request).await;
+
+    let labels = [("method", method.as_str()), ("route", route.as_str())];
+    metrics::observe(
+        metrics::HTTP_REQUEST_DURATION,
+        &labels,
+        started.elapsed().as_secs_f64(),
+    );
+    metrics::inc_counter(
+        metrics::HTTP_REQUESTS,
+        &[
+            ("method", method.as_str()),
+            ("route", route.as_str()),
+            ("status", response.status().as_str()),
+        ],
+        1,
+    );
+
+    response
+}
+
 pub async fn security_headers(request: Request, next: Next) -> Response {
     let mut response = next.run(request).await;
 
@@ -71,7 +101,7 @@ pub async fn only_ssl(request: Request, next: Next) -> Response {
         .and_then(|header| header.to_str().ok())
         == Some("true");
 
-    if ssl || Env::current().is_dev() {
+    if ssl || ApiConfig::current().env.is_dev() {
         next.run(request).await
     } else {
         let authority = request
@@ -96,16 +126,25 @@ pub async fn only_ssl(request: Request, next: Next) -> Response {
     }
 }
 
-pub fn init_logs(filename: &str) {
-    let file_appender = tracing_appender::rolling::never("./", filename);
-
-    let offset = UtcOffset::from_hms(1, 0, 0).expect("should get CET offset");
-    let time_format =
-        time::format_description::parse("[year]-[month]-[day]T[hour]:[minute]:[second]").unwrap();
-    let timer = OffsetTime::new(offset, time_format);
+pub fn init_logs(config: &ApiConfig, filename: &str) {
+    let file_appender = tracing_appender::rolling::never(&config.log_dir, filename);
 
-    tracing_subscriber::fmt()
+    let subscriber = tracing_subscriber::fmt()
         .with_writer(file_appender)
-        .with_timer(timer)
-        .init();
+        .with_timer(LocalTime(config.tz()));
+
+    match config.log_format {
+        LogFormat::Full => subscriber.init(),
+        LogFormat::Compact => subscriber.compact().init(),
+        LogFormat::Pretty => subscriber.pretty().init(),
+    }
+}
+
+struct LocalTime(chrono_tz::Tz);
+
+impl FormatTime for LocalTime {
+    fn format_time(&self, w: &mut Writer<'_>) -> std::fmt::Result {
+        let now = chrono::Utc::now().with_timezone(&self.0);
+        write!(w, "{}", now.format("%Y-%m-%dT%H:%M:%S"))
+    }
 }
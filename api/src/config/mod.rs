@@ -1,5 +1,9 @@
+pub mod api_config;
 pub mod middleware;
+pub mod openapi;
 pub mod routes;
 pub mod schedule;
+pub mod shutdown;
 
+pub use api_config::ApiConfig;
 pub use middleware::*;
@@ -0,0 +1,333 @@
+use axum::{http::Method, routing::MethodRouter};
+use serde_json::{json, Map, Value};
+
+use crate::config::routes::AppState;
+
+/// Route registered in the router and documented in the OpenAPI spec.
+pub struct Endpoint {
+    pub method: Method,
+    // Axum path syntax, e.g. `/channel_data/:handle`
+    pub path: &'static str,
+    pub summary: &'static str,
+    pub auth: bool,
+    // Throttled per client IP and API key by the `rate_limit` middleware
+    pub rate_limited: bool,
+    // Query param names and descriptions
+    pub query: &'static [(&'static str, &'static str)],
+    // Schema name of the JSON request body
+    pub request: Option<&'static str>,
+    // Status, description and schema name, responses without a schema are plain text
+    pub responses: &'static [(u16, &'static str, Option<&'static str>)],
+    pub handler: MethodRouter<AppState>,
+}
+
+pub fn spec(endpoints: &[Endpoint]) -> Value {
+    let mut paths = Map::new();
+
+    for endpoint in endpoints {
+        let (path, path_params) = openapi_path(endpoint.path);
+        let operation = operation(endpoint, &path_params);
+
+        paths
+            .entry(path)
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .expect("Path item is an object")
+            .insert(endpoint.method.as_str().to_lowercase(), operation);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "yt-sub API",
+            "description": "Remote accounts and channel lookups for the ytsub CLI",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "ApiKey": { "type": "apiKey", "in": "header", "name": "X-API-KEY" },
+            },
+            "schemas": schemas(),
+        },
+    })
+}
+
+// `/channel_data/:handle` becomes `/channel_data/{handle}`
+fn openapi_path(path: &str) -> (String, Vec<String>) {
+    let mut params = vec![];
+
+    let segments = path
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(param) => {
+                params.push(param.to_string());
+                format!("{{{param}}}")
+            }
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    (segments.join("/"), params)
+}
+
+fn operation(endpoint: &Endpoint, path_params: &[String]) -> Value {
+    let mut parameters = path_params
+        .iter()
+        .map(|name| {
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    parameters.extend(endpoint.query.iter().map(|(name, description)| {
+        json!({
+            "name": name,
+            "in": "query",
+            "required": true,
+            "description": description,
+            "schema": { "type": "string" },
+        })
+    }));
+
+    let mut responses = Map::new();
+    for (status, description, schema) in endpoint.responses {
+        responses.insert(status.to_string(), response(description, *schema));
+    }
+    if endpoint.auth {
+        responses.insert(
+            "401".to_string(),
+            response("Missing or invalid API key", None),
+        );
+    }
+    if endpoint.rate_limited {
+        responses.insert(
+            "429".to_string(),
+            response(
+                "Rate limit exceeded, retry after the number of seconds in the Retry-After header",
+                None,
+            ),
+        );
+    }
+
+    let mut operation = json!({
+        "summary": endpoint.summary,
+        "parameters": parameters,
+        "responses": responses,
+    });
+
+    if let Some(schema) = endpoint.request {
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema_ref(schema) } },
+        });
+    }
+
+    if endpoint.auth {
+        operation["security"] = json!([{ "ApiKey": [] }]);
+    }
+
+    operation
+}
+
+fn response(description: &str, schema: Option<&str>) -> Value {
+    let content = match schema {
+        Some(schema) => json!({ "application/json": { "schema": schema_ref(schema) } }),
+        None => json!({ "text/plain": { "schema": { "type": "string" } } }),
+    };
+
+    json!({ "description": description, "content": content })
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{name}") })
+}
+
+fn nullable(kind: &str) -> Value {
+    json!({ "type": kind, "nullable": true })
+}
+
+fn timestamp(nullable: bool) -> Value {
+    json!({ "type": "string", "format": "date-time", "nullable": nullable })
+}
+
+// Mirrors the request and response structs in `yt_sub_core::api` and `yt_sub_core::account`
+fn schemas() -> Value {
+    json!({
+        "Channel": {
+            "type": "object",
+            "required": ["handle", "description", "channel_id"],
+            "properties": {
+                "handle": { "type": "string" },
+                "description": { "type": "string" },
+                "channel_id": { "type": "string" },
+            },
+        },
+        "UserSettings": {
+            "type": "object",
+            "description": "Same format as the CLI config file",
+            "required": ["channels", "notifiers"],
+            "properties": {
+                "channels": { "type": "array", "items": schema_ref("Channel") },
+                "notifiers": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "description": "Notifier kind with its config, e.g. {\"Slack\": {\"webhook_url\": \"...\", \"channel\": \"...\"}}",
+                    },
+                },
+                "api_key": {
+                    "type": "string",
+                    "nullable": true,
+                    "deprecated": true,
+                    "description": "Use the X-API-KEY header, it must match the header if present",
+                },
+                "schedule": {
+                    "type": "object",
+                    "nullable": true,
+                    "description": "One of {\"Hours\": [8, 20]}, {\"Cron\": \"0 9 * * Mon-Fri\"} or {\"Blocks\": [...]}",
+                },
+                "templates": {
+                    "type": "object",
+                    "properties": {
+                        "video": { "type": "string" },
+                        "header": { "type": "string" },
+                        "footer": { "type": "string" },
+                    },
+                },
+            },
+        },
+        "ApiKeyResponse": {
+            "type": "object",
+            "required": ["api_key"],
+            "properties": { "api_key": { "type": "string" } },
+        },
+        "ChannelDataResponse": {
+            "type": "object",
+            "required": ["channel_id", "channel_name"],
+            "properties": {
+                "channel_id": { "type": "string" },
+                "channel_name": { "type": "string" },
+            },
+        },
+        "ChannelCandidate": {
+            "type": "object",
+            "required": ["handle", "channel_id", "title"],
+            "properties": {
+                "handle": { "type": "string" },
+                "channel_id": { "type": "string" },
+                "title": { "type": "string" },
+                "subscriber_count": { "type": "integer", "format": "int64", "nullable": true },
+                "thumbnail_url": nullable("string"),
+            },
+        },
+        "ChannelCandidates": { "type": "array", "items": schema_ref("ChannelCandidate") },
+        "SlackLinkResponse": {
+            "type": "object",
+            "required": ["code", "expires_at"],
+            "properties": {
+                "code": { "type": "string" },
+                "expires_at": timestamp(false),
+            },
+        },
+        "ResumeRequest": {
+            "type": "object",
+            "properties": {
+                "notifier_index": { "type": "integer", "nullable": true, "description": "Resumes all suspended notifiers if missing" },
+            },
+        },
+        "NotifierStatus": {
+            "type": "object",
+            "required": ["index", "name", "suspended", "consecutive_failures"],
+            "properties": {
+                "index": { "type": "integer" },
+                "name": { "type": "string" },
+                "suspended": { "type": "boolean" },
+                "consecutive_failures": { "type": "integer" },
+                "last_error": nullable("string"),
+                "suspended_at": timestamp(true),
+            },
+        },
+        "AccountStatus": {
+            "type": "object",
+            "required": ["notifiers"],
+            "properties": {
+                "notifiers": { "type": "array", "items": schema_ref("NotifierStatus") },
+            },
+        },
+        "Delivery": {
+            "type": "object",
+            "required": ["id", "notifier_index", "notifier_name", "message", "status", "attempts", "next_attempt_at", "created_at"],
+            "properties": {
+                "id": { "type": "integer", "format": "int64" },
+                "notifier_index": { "type": "integer" },
+                "notifier_name": { "type": "string" },
+                "message": { "type": "string" },
+                "status": { "type": "string", "enum": ["pending", "sending", "delivered", "dead"] },
+                "attempts": { "type": "integer" },
+                "last_error": nullable("string"),
+                "next_attempt_at": timestamp(false),
+                "created_at": timestamp(false),
+                "delivered_at": timestamp(true),
+            },
+        },
+        "DeliveriesResponse": {
+            "type": "object",
+            "required": ["deliveries"],
+            "properties": {
+                "deliveries": { "type": "array", "items": schema_ref("Delivery") },
+            },
+        },
+        "Account": {
+            "type": "object",
+            "required": ["settings", "notifiers", "deliveries"],
+            "properties": {
+                "settings": schema_ref("UserSettings"),
+                "last_run_at": timestamp(true),
+                "next_check_at": timestamp(true),
+                "notifiers": { "type": "array", "items": schema_ref("NotifierStatus") },
+                "deliveries": { "type": "array", "items": schema_ref("Delivery") },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::routes::endpoints;
+
+    use super::*;
+
+    #[test]
+    fn test_spec() {
+        let spec = spec(&endpoints());
+
+        let channel_data = &spec["paths"]["/channel_data/{handle}"]["get"];
+        assert_eq!(channel_data["parameters"][0]["name"], "handle");
+        assert_eq!(
+            channel_data["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ChannelDataResponse"
+        );
+
+        let account = &spec["paths"]["/account"];
+        for method in ["get", "post", "put", "delete"] {
+            assert!(account[method].is_object(), "Missing {method} /account");
+        }
+        assert_eq!(account["put"]["security"][0]["ApiKey"], json!([]));
+        assert!(account["post"]["security"].is_null());
+
+        // Every referenced schema is defined
+        let text = spec.to_string();
+        for name in text.split("#/components/schemas/").skip(1) {
+            let name = name.split('"').next().unwrap();
+            assert!(
+                spec["components"]["schemas"][name].is_object(),
+                "Missing schema {name}"
+            );
+        }
+    }
+}
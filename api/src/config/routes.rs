@@ -1,30 +1,255 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use crate::controllers;
+use crate::{
+    config::{
+        middleware,
+        openapi::{self, Endpoint},
+        ApiConfig,
+    },
+    controllers,
+    rate_limit::{self, RateLimits},
+    storage::Storage,
+};
 use axum::{
     body::Body,
-    http::{HeaderMap, HeaderValue, Response, StatusCode},
+    http::{header::RETRY_AFTER, HeaderMap, HeaderValue, Method, Response, StatusCode},
+    middleware::{from_fn, from_fn_with_state},
     response::IntoResponse,
     routing::{delete, get, post, put},
     Router,
 };
 use serde_json::Value;
-use sqlx::SqlitePool;
 
 #[derive(Clone, Debug)]
 pub struct AppState {
-    pub conn: Arc<SqlitePool>,
+    pub conn: Arc<dyn Storage>,
+    pub rate_limits: Arc<RateLimits>,
+}
+
+pub async fn app(conn: Arc<dyn Storage>) -> Router {
+    let state = AppState {
+        conn,
+        rate_limits: Arc::new(RateLimits::new(ApiConfig::current())),
+    };
+
+    router(state)
+}
+
+// The router and the `/openapi.json` document are both built from this list
+pub fn endpoints() -> Vec<Endpoint> {
+    vec![
+        Endpoint {
+            method: Method::GET,
+            path: "/channel_data/:handle",
+            summary: "Resolve a channel handle to its ID and name",
+            auth: false,
+            rate_limited: true,
+            query: &[],
+            request: None,
+            responses: &[
+                (200, "Channel data", Some("ChannelDataResponse")),
+                (400, "Invalid channel handle", None),
+                (404, "Channel not found", None),
+                (503, "YouTube API calls are throttled", None),
+            ],
+            handler: get(controllers::channels::show),
+        },
+        Endpoint {
+            method: Method::GET,
+            path: "/channel_search",
+            summary: "Search channels by name",
+            auth: false,
+            rate_limited: true,
+            query: &[("q", "Channel name or keywords")],
+            request: None,
+            responses: &[
+                (200, "Matching channels", Some("ChannelCandidates")),
+                (400, "Missing or invalid query", None),
+                (
+                    503,
+                    "YouTube API calls are throttled or not configured",
+                    None,
+                ),
+            ],
+            handler: get(controllers::channels::search),
+        },
+        Endpoint {
+            method: Method::POST,
+            path: "/account",
+            summary: "Register a remote account, a test message is sent to the Slack notifier",
+            auth: false,
+            rate_limited: true,
+            query: &[],
+            request: Some("UserSettings"),
+            responses: &[
+                (201, "API key of the new account", Some("ApiKeyResponse")),
+                (400, "Invalid settings", None),
+            ],
+            handler: post(controllers::account::create),
+        },
+        Endpoint {
+            method: Method::DELETE,
+            path: "/account",
+            summary: "Remove the remote account",
+            auth: true,
+            rate_limited: true,
+            query: &[],
+            request: None,
+            responses: &[(200, "Account removed", None)],
+            handler: delete(controllers::account::delete),
+        },
+        Endpoint {
+            method: Method::GET,
+            path: "/account",
+            summary: "Get the remote account settings, notifier health and recent deliveries",
+            auth: true,
+            rate_limited: true,
+            query: &[],
+            request: None,
+            responses: &[(200, "Remote account", Some("Account"))],
+            handler: get(controllers::account::show),
+        },
+        Endpoint {
+            method: Method::PUT,
+            path: "/account",
+            summary: "Replace the remote account settings",
+            auth: true,
+            rate_limited: true,
+            query: &[],
+            request: Some("UserSettings"),
+            responses: &[
+                (200, "Settings updated", None),
+                (400, "Invalid settings", None),
+            ],
+            handler: put(controllers::account::update),
+        },
+        Endpoint {
+            method: Method::GET,
+            path: "/account/deliveries",
+            summary: "List recent notification deliveries",
+            auth: true,
+            rate_limited: true,
+            query: &[],
+            request: None,
+            responses: &[(200, "Recent deliveries", Some("DeliveriesResponse"))],
+            handler: get(controllers::account::deliveries),
+        },
+        Endpoint {
+            method: Method::GET,
+            path: "/account/status",
+            summary: "Get notifier health",
+            auth: true,
+            rate_limited: true,
+            query: &[],
+            request: None,
+            responses: &[(200, "Notifier health", Some("AccountStatus"))],
+            handler: get(controllers::account::status),
+        },
+        Endpoint {
+            method: Method::POST,
+            path: "/account/resume",
+            summary: "Resume suspended notifiers after a successful test message",
+            auth: true,
+            rate_limited: true,
+            query: &[],
+            request: Some("ResumeRequest"),
+            responses: &[
+                (200, "Updated notifier health", Some("AccountStatus")),
+                (400, "Notifier is still failing", None),
+            ],
+            handler: post(controllers::account::resume),
+        },
+        Endpoint {
+            method: Method::POST,
+            path: "/account/rotate_key",
+            summary: "Replace the API key, the previous key stops working immediately",
+            auth: true,
+            rate_limited: true,
+            query: &[],
+            request: None,
+            responses: &[(200, "New API key", Some("ApiKeyResponse"))],
+            handler: post(controllers::account::rotate_key),
+        },
+        Endpoint {
+            method: Method::POST,
+            path: "/account/slack_link",
+            summary: "Create a one-time code for linking a Slack channel with `/ytsub link CODE`",
+            auth: true,
+            rate_limited: true,
+            query: &[],
+            request: None,
+            responses: &[(200, "Link code", Some("SlackLinkResponse"))],
+            handler: post(controllers::slack::link_code),
+        },
+        Endpoint {
+            method: Method::POST,
+            path: "/slack/commands",
+            summary: "Slack slash command webhook, requests are verified with the signing secret",
+            auth: false,
+            rate_limited: false,
+            query: &[],
+            request: None,
+            responses: &[
+                (200, "Slack message", None),
+                (401, "Invalid Slack signature", None),
+                (503, "Slack signing secret is not configured", None),
+            ],
+            handler: post(controllers::slack::command),
+        },
+        Endpoint {
+            method: Method::GET,
+            path: "/uptime",
+            summary: "Health check",
+            auth: false,
+            rate_limited: false,
+            query: &[],
+            request: None,
+            responses: &[(200, "OK", None)],
+            handler: get(|| async move { "OK".into_response() }),
+        },
+        Endpoint {
+            method: Method::GET,
+            path: "/metrics",
+            summary: "Prometheus metrics",
+            auth: false,
+            rate_limited: false,
+            query: &[],
+            request: None,
+            responses: &[(200, "Metrics in the Prometheus text format", None)],
+            handler: get(controllers::metrics::show),
+        },
+        Endpoint {
+            method: Method::GET,
+            path: "/openapi.json",
+            summary: "This document",
+            auth: false,
+            rate_limited: false,
+            query: &[],
+            request: None,
+            responses: &[(200, "OpenAPI document", None)],
+            handler: get(
+                || async move { json_response(openapi::spec(&endpoints()), StatusCode::OK) },
+            ),
+        },
+    ]
 }
 
-pub async fn app(conn: Arc<SqlitePool>) -> Router {
-    let state = AppState { conn };
+fn router(state: AppState) -> Router {
+    endpoints()
+        .into_iter()
+        .fold(Router::new(), |router, endpoint| {
+            // Health checks, metrics scrapes and Slack commands share proxy or Slack IPs
+            let handler = if endpoint.rate_limited {
+                endpoint
+                    .handler
+                    .route_layer(from_fn_with_state(state.clone(), rate_limit::rate_limit))
+            } else {
+                endpoint.handler
+            };
 
-    Router::new()
-        .route("/channel_data/:handle", get(controllers::channels::show))
-        .route("/account", post(controllers::account::create))
-        .route("/account", delete(controllers::account::delete))
-        .route("/account", put(controllers::account::update))
-        .route("/uptime", get(|| async move { "OK".into_response() }))
+            router.route(endpoint.path, handler)
+        })
+        .route_layer(from_fn(middleware::track_metrics))
         .with_state(state)
 }
 
@@ -32,6 +257,21 @@ pub fn invalid_req(reason: &str) -> Response<Body> {
     (StatusCode::BAD_REQUEST, reason.to_string()).into_response()
 }
 
+pub fn unauthorized(reason: &str) -> Response<Body> {
+    (StatusCode::UNAUTHORIZED, reason.to_string()).into_response()
+}
+
+pub fn too_many_requests(retry_after: Duration) -> Response<Body> {
+    let retry_after = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(RETRY_AFTER, retry_after.to_string())],
+        "Too many requests",
+    )
+        .into_response()
+}
+
 pub fn json_response(body: Value, status: StatusCode) -> Response<Body> {
     let mut headers = HeaderMap::new();
     headers.insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -41,7 +281,7 @@ pub fn json_response(body: Value, status: StatusCode) -> Response<Body> {
 
 #[cfg(test)]
 mod tests {
-    use crate::lite_helpers::tests::setup_test_db;
+    use crate::storage::tests::setup_test_db;
 
     use super::*;
     use axum::http::Request;
@@ -70,4 +310,167 @@ mod tests {
         assert_eq!(body, "OK");
         Ok(())
     }
+
+    #[tokio::test]
+    async fn slack_commands_without_secret_test() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let app = app(conn).await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/slack/commands")
+                    .body(Body::from("text=list&channel_id=C123"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn metrics_test() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let app = app(conn).await;
+
+        for uri in ["/uptime", "/metrics"] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            if uri == "/metrics" {
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                let body = String::from_utf8(body.to_vec())?;
+
+                assert!(body.contains("ytsub_users 0\n"));
+                assert!(body.contains(
+                    "ytsub_http_requests_total{method=\"GET\",route=\"/uptime\",status=\"200\"}"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rate_limit_test() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let config = ApiConfig {
+            ip_rate_limit_per_minute: 2,
+            ..ApiConfig::default()
+        };
+        let app = router(AppState {
+            conn,
+            rate_limits: Arc::new(RateLimits::new(&config)),
+        });
+
+        let request = |ip: &str| {
+            Request::builder()
+                .uri("/channel_search")
+                .header("x-forwarded-for", ip)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        for _ in 0..2 {
+            let response = app.clone().oneshot(request("1.1.1.1")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+
+        let response = app.clone().oneshot(request("1.1.1.1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "30");
+
+        let response = app.clone().oneshot(request("2.2.2.2")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn not_rate_limited_test() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let config = ApiConfig {
+            ip_rate_limit_per_minute: 1,
+            ..ApiConfig::default()
+        };
+        let app = router(AppState {
+            conn,
+            rate_limits: Arc::new(RateLimits::new(&config)),
+        });
+
+        let request = |method: &str, uri: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("x-forwarded-for", "1.1.1.1")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // The signing secret is not configured in tests, so Slack commands get a 503
+        for _ in 0..3 {
+            let response = app
+                .clone()
+                .oneshot(request("GET", "/uptime"))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = app
+                .clone()
+                .oneshot(request("POST", "/slack/commands"))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        }
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/channel_search"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/channel_search"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn openapi_test() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let app = app(conn).await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let spec: Value = serde_json::from_slice(&body)?;
+
+        assert_eq!(spec["openapi"], "3.0.3");
+        assert!(spec["paths"]["/channel_search"]["get"].is_object());
+        assert!(spec["paths"]["/openapi.json"]["get"].is_object());
+
+        Ok(())
+    }
 }
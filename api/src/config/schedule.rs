@@ -1,40 +1,80 @@
+use std::sync::Arc;
+
 use eyre::Result;
 use tokio_cron_scheduler::{Job, JobScheduler};
+use tokio_util::task::TaskTracker;
 
-use crate::tasks;
+use crate::{config::ApiConfig, storage::Storage, tasks};
 
-pub async fn get_schedule() -> Result<JobScheduler> {
+// Jobs register in `jobs` while running, so that shutdown can wait for in-flight notifications
+pub async fn get_schedule(
+    config: &ApiConfig,
+    conn: Arc<dyn Storage>,
+    jobs: TaskTracker,
+) -> Result<JobScheduler> {
     let mut sched = JobScheduler::new().await?;
 
+    let (check_conn, check_jobs) = (conn.clone(), jobs.clone());
     sched
-        .add(Job::new_async("every 1 hour", |_uuid, _l| {
-            Box::pin(async move {
-                tracing::info!("Checking for new videos");
-                match tasks::run_check_videos().await {
-                    Ok(_) => {}
-                    Err(e) => {
-                        tracing::error!("Failed to check videos: {}", &e);
+        .add(Job::new_async(
+            config.check_videos_cron().as_str(),
+            move |_uuid, _l| {
+                let (conn, jobs) = (check_conn.clone(), check_jobs.clone());
+                Box::pin(async move {
+                    if jobs.is_closed() {
+                        return;
                     }
-                }
-            })
-        })?)
+                    let _running = jobs.token();
+
+                    tracing::info!("Checking for new videos");
+                    match tasks::run_check_videos(conn.as_ref()).await {
+                        Ok(_) => {}
+                        Err(e) => {
+                            tracing::error!("Failed to check videos: {}", &e);
+                        }
+                    }
+                })
+            },
+        )?)
         .await?;
 
     sched
-        .add(Job::new_async("every 5 minutes", |_uuid, _l| {
+        .add(Job::new_async("0 * * * * *", move |_uuid, _l| {
+            let (conn, jobs) = (conn.clone(), jobs.clone());
             Box::pin(async move {
-                tracing::info!("Uptime ping");
-                match tasks::run_uptime_ping().await {
+                if jobs.is_closed() {
+                    return;
+                }
+                let _running = jobs.token();
+
+                match tasks::run_deliver_outbox(conn.as_ref()).await {
                     Ok(_) => {}
                     Err(e) => {
-                        tracing::error!("Failed to uptime ping: {}", &e);
+                        tracing::error!("Failed to deliver outbox: {}", &e);
                     }
                 }
             })
         })?)
         .await?;
 
-    sched.shutdown_on_ctrl_c();
+    if let Some(uptime_url) = &config.uptime_url {
+        let uptime_url = uptime_url.clone();
+
+        sched
+            .add(Job::new_async("every 5 minutes", move |_uuid, _l| {
+                let uptime_url = uptime_url.clone();
+                Box::pin(async move {
+                    tracing::info!("Uptime ping");
+                    match tasks::run_uptime_ping(&uptime_url).await {
+                        Ok(_) => {}
+                        Err(e) => {
+                            tracing::error!("Failed to uptime ping: {}", &e);
+                        }
+                    }
+                })
+            })?)
+            .await?;
+    }
 
     sched.set_shutdown_handler(Box::new(|| {
         Box::pin(async move {
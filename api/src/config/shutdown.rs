@@ -0,0 +1,72 @@
+use tokio::signal;
+use tokio_util::task::TaskTracker;
+
+// Resolves on Ctrl+C or SIGTERM
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutdown signal received");
+}
+
+// No new jobs are started after this call, running ones are awaited
+pub async fn wait_for_jobs(jobs: &TaskTracker) {
+    jobs.close();
+
+    if !jobs.is_empty() {
+        tracing::info!("Waiting for {} running jobs", jobs.len());
+    }
+
+    jobs.wait().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_for_jobs() {
+        let jobs = TaskTracker::new();
+        let finished = Arc::new(AtomicBool::new(false));
+
+        let running = jobs.token();
+        let job_finished = finished.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            job_finished.store(true, Ordering::SeqCst);
+            drop(running);
+        });
+
+        wait_for_jobs(&jobs).await;
+
+        assert!(finished.load(Ordering::SeqCst));
+        assert!(jobs.is_closed());
+    }
+}
@@ -1,42 +1,48 @@
-use axum::{extract::State, http::HeaderMap, response::IntoResponse, Json};
+use axum::{extract::State, http::HeaderMap, response::IntoResponse, Extension, Json};
+use chrono::{DateTime, Utc};
 use eyre::Result;
 use reqwest::StatusCode;
 use serde_json::{json, Value};
-use sqlx::SqlitePool;
-use uuid::Uuid;
-use yt_sub_core::UserSettings;
+use yt_sub_core::{
+    account::{Account, Delivery},
+    api::{ApiKeyResponse, DeliveriesResponse, ResumeRequest},
+    UserSettings,
+};
 
 use crate::{
-    config::routes::{invalid_req, json_response, AppState},
-    lite_helpers::UserRow,
+    auth::{self, authenticate},
+    config::{
+        routes::{invalid_req, json_response, too_many_requests, unauthorized, AppState},
+        ApiConfig,
+    },
+    rate_limit::ClientIp,
+    storage::Storage,
     user_settings_api::UserSettingsAPI,
 };
 
 pub async fn update(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(settings): Json<UserSettings>,
 ) -> impl IntoResponse {
-    let conn = &state.conn.clone();
-    let Some(api_key) = settings.api_key.clone() else {
-        return invalid_req("Missing API key!");
+    let conn = state.conn.as_ref();
+    let user_id = match authenticate(&headers, conn).await {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(&e.to_string()),
     };
 
-    let exists = match UserRow::exists(&api_key, conn).await {
-        Ok(exists) => exists,
-        Err(e) => {
-            return invalid_req(&e.to_string());
+    if let Some(api_key) = &settings.api_key {
+        if auth::user_id(api_key) != user_id {
+            return invalid_req("API key in the request body does not match the X-API-KEY header");
         }
-    };
-
-    if !exists {
-        return invalid_req("Invalid API key!");
     }
 
     if settings.get_slack_notifier().is_none() {
         return invalid_req("Missing Slack notifier settings");
     }
 
-    match settings.save(conn).await {
+    // Notifier state is moved or reset by `save` when notifiers change
+    match settings.save(&user_id, conn).await {
         Ok(_) => {}
         Err(e) => {
             return invalid_req(&e.to_string());
@@ -46,22 +52,102 @@ pub async fn update(
     "UPDATED".into_response()
 }
 
-pub async fn delete(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
-    let conn = &state.conn.clone();
-    let api_key = match headers.get("X-API-KEY") {
-        Some(api_key) => api_key.to_str().unwrap(),
-        None => return invalid_req("Missing X-API-KEY header"),
+pub async fn status(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let conn = state.conn.as_ref();
+    let user_id = match authenticate(&headers, conn).await {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(&e.to_string()),
     };
 
-    match UserSettings::read(api_key, conn).await {
-        Ok(_) => {}
-        Err(e) => {
-            return invalid_req(&e.to_string());
+    let settings = match UserSettings::read(&user_id, conn).await {
+        Ok(settings) => settings,
+        Err(e) => return invalid_req(&e.to_string()),
+    };
+
+    match settings.account_status(&user_id, conn).await {
+        Ok(status) => json_response(json!(status), StatusCode::OK),
+        Err(e) => invalid_req(&e.to_string()),
+    }
+}
+
+pub async fn resume(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(params): Json<ResumeRequest>,
+) -> impl IntoResponse {
+    let conn = state.conn.as_ref();
+    let user_id = match authenticate(&headers, conn).await {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(&e.to_string()),
+    };
+
+    match resume_impl(&user_id, params, conn).await {
+        Ok(response) => json_response(response, StatusCode::OK),
+        Err(e) => invalid_req(&e.to_string()),
+    }
+}
+
+// Suspended notifiers are re-enabled only if a test message goes through
+async fn resume_impl(user_id: &str, params: ResumeRequest, conn: &dyn Storage) -> Result<Value> {
+    let settings = UserSettings::read(user_id, conn).await?;
+
+    if let Some(index) = params.notifier_index {
+        if index >= settings.notifiers.len() {
+            eyre::bail!("Invalid notifier index {index}");
+        }
+    }
+
+    let status = settings.account_status(user_id, conn).await?;
+
+    for notifier_status in status.suspended() {
+        if params
+            .notifier_index
+            .is_some_and(|index| index != notifier_status.index)
+        {
+            continue;
         }
+
+        settings.notifiers[notifier_status.index]
+            .verify("Notifications resumed. You'll receive notifications about new videos.")
+            .await
+            .map_err(|e| eyre::eyre!("Notifier {} is still failing: {e}", notifier_status.index))?;
+
+        conn.resume_notifier(user_id, notifier_status.index).await?;
     }
 
+    Ok(json!(settings.account_status(user_id, conn).await?))
+}
+
+pub async fn rotate_key(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let conn = state.conn.as_ref();
+    let user_id = match authenticate(&headers, conn).await {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(&e.to_string()),
+    };
+
+    match rotate_key_impl(&user_id, conn).await {
+        Ok(response) => json_response(response, StatusCode::OK),
+        Err(e) => invalid_req(&e.to_string()),
+    }
+}
+
+// The previous key stops working immediately
+async fn rotate_key_impl(user_id: &str, conn: &dyn Storage) -> Result<Value> {
+    let api_key = auth::generate_api_key();
+    conn.rename_user(user_id, &auth::user_id(&api_key)).await?;
+
+    Ok(json!(ApiKeyResponse { api_key }))
+}
+
+pub async fn delete(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let conn = state.conn.as_ref();
+    let user_id = match authenticate(&headers, conn).await {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(&e.to_string()),
+    };
+
     // TODO macro
-    match UserSettings::delete(api_key, conn).await {
+    match UserSettings::delete(&user_id, conn).await {
         Ok(_) => {}
         Err(e) => {
             return invalid_req(&e.to_string());
@@ -71,11 +157,73 @@ pub async fn delete(State(state): State<AppState>, headers: HeaderMap) -> impl I
     "DELETED".into_response()
 }
 
+const DELIVERIES_LIMIT: i64 = 50;
+
+pub async fn deliveries(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let conn = state.conn.as_ref();
+    let user_id = match authenticate(&headers, conn).await {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(&e.to_string()),
+    };
+
+    match conn.outbox_history(&user_id, DELIVERIES_LIMIT).await {
+        Ok(rows) => {
+            let deliveries = rows.into_iter().map(Delivery::from).collect::<Vec<_>>();
+            json_response(json!(DeliveriesResponse { deliveries }), StatusCode::OK)
+        }
+        Err(e) => invalid_req(&e.to_string()),
+    }
+}
+
+pub async fn show(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let conn = state.conn.as_ref();
+    let user_id = match authenticate(&headers, conn).await {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(&e.to_string()),
+    };
+
+    match show_impl(&user_id, Utc::now(), conn).await {
+        Ok(account) => json_response(json!(account), StatusCode::OK),
+        Err(e) => invalid_req(&e.to_string()),
+    }
+}
+
+const RECENT_DELIVERIES_LIMIT: i64 = 10;
+
+async fn show_impl(user_id: &str, now: DateTime<Utc>, conn: &dyn Storage) -> Result<Account> {
+    let settings = UserSettings::read(user_id, conn).await?;
+    let last_run_at = UserSettings::last_run_at(user_id, conn).await?;
+    let status = settings.account_status(user_id, conn).await?;
+
+    let deliveries = conn
+        .outbox_history(user_id, RECENT_DELIVERIES_LIMIT)
+        .await?
+        .into_iter()
+        .map(Delivery::from)
+        .collect();
+
+    Ok(Account {
+        next_check_at: settings
+            .next_due_at(last_run_at, now)
+            .map(|due_at| ApiConfig::current().next_check_at(due_at)),
+        last_run_at,
+        notifiers: status.notifiers,
+        deliveries,
+        settings,
+    })
+}
+
 pub async fn create(
     State(state): State<AppState>,
+    Extension(ClientIp(ip)): Extension<ClientIp>,
     Json(settings): Json<UserSettings>,
 ) -> impl IntoResponse {
-    let conn = &state.conn.clone();
+    // Failed attempts count too, registration sends a message to the provided webhook
+    if let Err(retry_after) = state.rate_limits.accounts.check(&ip) {
+        return too_many_requests(retry_after);
+    }
+
+    let conn = state.conn.as_ref();
     let response = match create_impl(settings, conn).await {
         Ok(response) => response,
         Err(e) => return invalid_req(&e.to_string()).into_response(),
@@ -84,9 +232,9 @@ pub async fn create(
     json_response(response, StatusCode::CREATED)
 }
 
-async fn create_impl(settings: UserSettings, conn: &SqlitePool) -> Result<Value> {
+async fn create_impl(settings: UserSettings, conn: &dyn Storage) -> Result<Value> {
     if let Some(api_key) = settings.api_key {
-        if UserRow::exists(&api_key, conn).await? {
+        if conn.user_exists(&auth::user_id(&api_key)).await? {
             eyre::bail!("Already registered with this API key");
         } else {
             eyre::bail!("Invalid API key present. Please remove it and try again.");
@@ -101,13 +249,7 @@ async fn create_impl(settings: UserSettings, conn: &SqlitePool) -> Result<Value>
     };
 
     notifier
-        .notify(
-            vec![
-                "Registered remote account. You'll receive notifications about new videos."
-                    .to_string(),
-            ],
-            false,
-        )
+        .verify("Registered remote account. You'll receive notifications about new videos.")
         .await
         .map_err(|e| {
             eyre::eyre!(
@@ -116,27 +258,20 @@ async fn create_impl(settings: UserSettings, conn: &SqlitePool) -> Result<Value>
             )
         })?;
 
-    let api_key = Uuid::new_v4().to_string();
-
-    let settings = UserSettings {
-        api_key: Some(api_key.clone()),
-        ..settings
-    };
-
-    settings.save(conn).await?;
+    let api_key = auth::generate_api_key();
+    settings.save(&auth::user_id(&api_key), conn).await?;
 
-    Ok(json!({
-        "api_key": api_key,
-    }))
+    Ok(json!(ApiKeyResponse { api_key }))
 }
 
 #[cfg(test)]
 pub mod tests {
     use mockito::Server;
     use std::path::PathBuf;
+    use uuid::Uuid;
     use yt_sub_core::notifier::{Notifier, SlackConfig};
 
-    use crate::lite_helpers::tests::setup_test_db;
+    use crate::storage::{tests::setup_test_db, NOTIFIER_SUSPEND_THRESHOLD};
 
     use super::*;
     #[tokio::test]
@@ -154,13 +289,13 @@ pub mod tests {
             .await;
 
         let settings = build_settings(false, Some(format!("{}/slack_webhook", host)));
-        let json = create_impl(settings, &conn)
+        let json = create_impl(settings, &*conn)
             .await
             .expect("Failed to register user");
 
         let api_key = json["api_key"].as_str().unwrap();
 
-        let exists = UserRow::exists(api_key, &conn).await?;
+        let exists = conn.user_exists(&auth::user_id(api_key)).await?;
         assert!(exists);
 
         m.assert_async().await;
@@ -173,7 +308,7 @@ pub mod tests {
         let (conn, _cl) = setup_test_db().await;
         let settings = build_settings(true, None);
 
-        if let Err(e) = create_impl(settings, &conn).await {
+        if let Err(e) = create_impl(settings, &*conn).await {
             assert!(e.to_string().contains("Invalid API key present"));
         } else {
             panic!("Expected an error!");
@@ -187,7 +322,7 @@ pub mod tests {
         let (conn, _cl) = setup_test_db().await;
         let settings = build_settings(false, None);
 
-        if let Err(e) = create_impl(settings, &conn).await {
+        if let Err(e) = create_impl(settings, &*conn).await {
             assert!(e.to_string().contains("Missing Slack notifier settings"));
         } else {
             panic!("Expected an error!");
@@ -212,7 +347,7 @@ pub mod tests {
 
         let settings = build_settings(false, Some(format!("{}/slack_webhook", host)));
 
-        if let Err(e) = create_impl(settings, &conn).await {
+        if let Err(e) = create_impl(settings, &*conn).await {
             assert!(e.to_string().contains("Invalid slack webhook URL"));
         } else {
             panic!("Expected an error!");
@@ -223,6 +358,86 @@ pub mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_resume_suspended_notifier() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+        let m = server
+            .mock("POST", "/slack_webhook")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let settings = build_settings(true, Some(format!("{}/slack_webhook", host)));
+        let user_id = settings.user_id();
+        settings.save(&user_id, &*conn).await?;
+
+        for _ in 0..NOTIFIER_SUSPEND_THRESHOLD {
+            conn.record_failure(&user_id, 0, "invalid_token", Utc::now())
+                .await?;
+        }
+        let status = settings.account_status(&user_id, &*conn).await?;
+        assert_eq!(status.suspended().len(), 1);
+
+        let params = ResumeRequest {
+            notifier_index: None,
+        };
+        let response = resume_impl(&user_id, params, &*conn).await?;
+        assert_eq!(response["notifiers"][0]["suspended"], false);
+        let status = settings.account_status(&user_id, &*conn).await?;
+        assert!(status.suspended().is_empty());
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_show_account() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+
+        let settings = build_settings(true, Some("https://slack.com/webhook".to_string()));
+        let user_id = settings.user_id();
+        settings.save(&user_id, &*conn).await?;
+
+        conn.enqueue_outbox(&user_id, 0, &settings.notifiers[0], &[], "New video")
+            .await?;
+
+        let now: DateTime<Utc> = "2024-11-07T08:30:00Z".parse()?;
+        let account = show_impl(&user_id, now, &*conn).await?;
+
+        assert_eq!(account.settings.channels, settings.channels);
+        assert_eq!(account.last_run_at, None);
+        assert_eq!(account.next_check_at, Some("2024-11-07T09:00:00Z".parse()?));
+        assert_eq!(account.notifiers[0].name, "Slack");
+        assert_eq!(account.deliveries.len(), 1);
+        assert_eq!(account.deliveries[0].message, "New video");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rotate_key() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+
+        let settings = build_settings(true, Some("https://slack.com/webhook".to_string()));
+        let user_id = settings.user_id();
+        settings.save(&user_id, &*conn).await?;
+        conn.enqueue_outbox(&user_id, 0, &settings.notifiers[0], &[], "New video")
+            .await?;
+
+        let response = rotate_key_impl(&user_id, &*conn).await?;
+        let new_user_id = auth::user_id(response["api_key"].as_str().unwrap());
+
+        assert!(!conn.user_exists(&user_id).await?);
+        assert!(conn.user_exists(&new_user_id).await?);
+        assert_eq!(conn.outbox_history(&new_user_id, 10).await?.len(), 1);
+
+        Ok(())
+    }
+
     pub fn build_settings(with_api_key: bool, slack_webhook: Option<String>) -> UserSettings {
         let settings = UserSettings::default(PathBuf::from("test.toml"));
 
@@ -239,6 +454,10 @@ pub mod tests {
             let notifier = Notifier::Slack(SlackConfig {
                 webhook_url: webhook,
                 channel: "test".to_string(),
+                schedule: None,
+                digest: false,
+                blocks: false,
+                templates: Default::default(),
             });
 
             UserSettings {
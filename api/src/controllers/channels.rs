@@ -1,35 +1,183 @@
-use axum::{extract::Path, response::IntoResponse};
-use eyre::Result;
+use std::fmt;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+};
+use chrono::{DateTime, Duration, Utc};
+use eyre::{OptionExt, Result};
 use reqwest::{
-    header::{HeaderMap as ReHeaderMap, CONTENT_TYPE},
+    header::{HeaderMap as ReHeaderMap, CONTENT_TYPE, COOKIE},
     StatusCode,
 };
+use serde::Deserialize;
 use serde_json::{json, Value};
+use yt_sub_core::{api::ChannelDataResponse, channel::ChannelCandidate};
+
+use crate::{
+    config::{
+        routes::{invalid_req, json_response, AppState},
+        ApiConfig,
+    },
+    metrics,
+    storage::{ChannelHandleRow, ChannelSearchRow, Storage},
+};
+
+// https://developers.google.com/youtube/v3/determine_quota_cost
+const CHANNELS_LIST_QUOTA_COST: u64 = 1;
+const SEARCH_LIST_QUOTA_COST: u64 = 100;
+
+const YOUTUBE_API_HOST: &str = "https://www.googleapis.com";
+const YOUTUBE_HOST: &str = "https://www.youtube.com";
+
+// Handles rarely change owners, missing handles are rechecked sooner in case they get claimed
+const FOUND_TTL_DAYS: i64 = 7;
+const NOT_FOUND_TTL_HOURS: i64 = 1;
+const SEARCH_TTL_DAYS: i64 = 1;
+
+const SEARCH_MAX_RESULTS: u32 = 10;
+const SEARCH_MAX_QUERY_LENGTH: usize = 100;
+
+// YouTube refused the lookup, reported as 503 which the CLI explains to users
+#[derive(Debug)]
+pub struct Throttled;
 
-use crate::config::routes::{invalid_req, json_response};
+impl fmt::Display for Throttled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "YouTube API calls are currently throttled")
+    }
+}
 
+impl std::error::Error for Throttled {}
+
+// The server was started without a YouTube Data API key
 #[derive(Debug)]
-pub struct ChannelData {
-    pub channel_id: String,
-    pub channel_name: String,
+pub struct MissingApiKey;
+
+impl fmt::Display for MissingApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "YouTube API key is not configured")
+    }
+}
+
+impl std::error::Error for MissingApiKey {}
+
+// YouTube endpoints and the Data API key, tests point the hosts at a mock server
+struct YouTube<'a> {
+    api_key: Option<&'a str>,
+    api_host: &'a str,
+    page_host: &'a str,
 }
 
-pub async fn show(handle: Path<String>) -> impl IntoResponse {
-    let response = match show_impl(Some(handle.to_string())).await {
+impl YouTube<'static> {
+    fn from_config() -> Self {
+        Self {
+            api_key: ApiConfig::current().youtube_api_key.as_deref(),
+            api_host: YOUTUBE_API_HOST,
+            page_host: YOUTUBE_HOST,
+        }
+    }
+}
+
+fn is_unavailable(error: &eyre::Report) -> bool {
+    error.is::<Throttled>() || error.is::<MissingApiKey>()
+}
+
+pub async fn show(State(state): State<AppState>, handle: Path<String>) -> impl IntoResponse {
+    let response = match show_impl(Some(handle.to_string()), state.conn.as_ref()).await {
         Ok(Some(response)) => response,
         Ok(None) => {
             return (StatusCode::NOT_FOUND, "Channel not found").into_response();
         }
+        Err(e) if is_unavailable(&e) => {
+            return (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response();
+        }
         Err(e) => return invalid_req(&e.to_string()),
     };
 
-    json_response(response, StatusCode::OK)
+    json_response(json!(response), StatusCode::OK)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchParams {
+    q: Option<String>,
+}
+
+pub async fn search(
+    State(state): State<AppState>,
+    Query(params): Query<SearchParams>,
+) -> impl IntoResponse {
+    match search_impl(params.q, state.conn.as_ref()).await {
+        Ok(candidates) => json_response(json!(candidates), StatusCode::OK),
+        Err(e) if is_unavailable(&e) => {
+            (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response()
+        }
+        Err(e) => invalid_req(&e.to_string()),
+    }
+}
+
+pub(crate) async fn search_impl(
+    query: Option<String>,
+    conn: &dyn Storage,
+) -> Result<Vec<ChannelCandidate>> {
+    // Normalized so equivalent queries share a cache entry
+    let query = query
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+
+    if query.is_empty() {
+        eyre::bail!("Missing q query param")
+    }
+
+    if query.chars().count() > SEARCH_MAX_QUERY_LENGTH {
+        eyre::bail!("Search query is too long")
+    }
+
+    search_channels(&query, conn, &YouTube::from_config(), Utc::now()).await
 }
 
-async fn show_impl(handle: Option<String>) -> Result<Option<Value>> {
-    let youtube_api_key =
-        std::env::var("YOUTUBE_API_KEY").expect("Missing YOUTUBE_API_KEY env var");
+async fn search_channels(
+    query: &str,
+    conn: &dyn Storage,
+    youtube: &YouTube<'_>,
+    now: DateTime<Utc>,
+) -> Result<Vec<ChannelCandidate>> {
+    if let Some(row) = conn.get_channel_search(query).await? {
+        if row.fetched_at + Duration::days(SEARCH_TTL_DAYS) > now {
+            return row.results();
+        }
+    }
+
+    let candidates = fetch_candidates(query, youtube).await?;
+
+    conn.store_channel_search(&ChannelSearchRow {
+        query: query.to_string(),
+        results_json: serde_json::to_string(&candidates)?,
+        fetched_at: now,
+    })
+    .await?;
+
+    // Following a search result resolves its handle, so it's cached upfront
+    for candidate in &candidates {
+        conn.store_channel_handle(&ChannelHandleRow {
+            handle: candidate.handle.to_lowercase(),
+            channel_id: Some(candidate.channel_id.clone()),
+            channel_name: Some(candidate.title.clone()),
+            fetched_at: now,
+        })
+        .await?;
+    }
+
+    Ok(candidates)
+}
 
+pub(crate) async fn show_impl(
+    handle: Option<String>,
+    conn: &dyn Storage,
+) -> Result<Option<ChannelDataResponse>> {
     let handle = match handle {
         Some(handle) => handle,
         None => {
@@ -37,30 +185,491 @@ async fn show_impl(handle: Option<String>) -> Result<Option<Value>> {
         }
     };
 
+    resolve(
+        &normalize_handle(&handle),
+        conn,
+        &YouTube::from_config(),
+        Utc::now(),
+    )
+    .await
+}
+
+async fn resolve(
+    handle: &str,
+    conn: &dyn Storage,
+    youtube: &YouTube<'_>,
+    now: DateTime<Utc>,
+) -> Result<Option<ChannelDataResponse>> {
+    // Handles end up in the channel page URL and the cache key
+    if !is_valid_handle(handle) {
+        eyre::bail!("Invalid channel handle")
+    }
+
+    // Handles are case insensitive
+    let key = handle.to_lowercase();
+
+    if let Some(row) = conn.get_channel_handle(&key).await? {
+        if is_fresh(&row, now) {
+            return Ok(row
+                .channel_id
+                .zip(row.channel_name)
+                .map(|(channel_id, channel_name)| ChannelDataResponse {
+                    channel_id,
+                    channel_name,
+                }));
+        }
+    }
+
+    let channel = match fetch_channel(handle, youtube).await {
+        Err(e) if is_unavailable(&e) => scrape_channel(handle, youtube.page_host).await?,
+        result => result?,
+    };
+
+    conn.store_channel_handle(&ChannelHandleRow {
+        handle: key,
+        channel_id: channel.as_ref().map(|c| c.channel_id.clone()),
+        channel_name: channel.as_ref().map(|c| c.channel_name.clone()),
+        fetched_at: now,
+    })
+    .await?;
+
+    Ok(channel)
+}
+
+fn is_fresh(row: &ChannelHandleRow, now: DateTime<Utc>) -> bool {
+    let ttl = if row.channel_id.is_some() {
+        Duration::days(FOUND_TTL_DAYS)
+    } else {
+        Duration::hours(NOT_FOUND_TTL_HOURS)
+    };
+
+    row.fetched_at + ttl > now
+}
+
+async fn fetch_channel(handle: &str, youtube: &YouTube<'_>) -> Result<Option<ChannelDataResponse>> {
+    let json = youtube_api(
+        youtube,
+        "channels",
+        &[
+            ("forHandle", handle),
+            ("part", "snippet,id"),
+            ("maxResults", "1"),
+        ],
+        CHANNELS_LIST_QUOTA_COST,
+    )
+    .await?;
+    let results = json["pageInfo"]["totalResults"]
+        .as_i64()
+        .unwrap_or_default();
+
+    if results == 0 {
+        return Ok(None);
+    }
+
+    let channel_id = json["items"][0]["id"]
+        .as_str()
+        .ok_or_eyre("Missing channel ID in the YouTube API response")?;
+    let channel_name = json["items"][0]["snippet"]["title"]
+        .as_str()
+        .ok_or_eyre("Missing channel title in the YouTube API response")?;
+
+    Ok(Some(ChannelDataResponse {
+        channel_id: channel_id.to_string(),
+        channel_name: channel_name.to_string(),
+    }))
+}
+
+async fn fetch_candidates(query: &str, youtube: &YouTube<'_>) -> Result<Vec<ChannelCandidate>> {
+    let max_results = SEARCH_MAX_RESULTS.to_string();
+    let json = youtube_api(
+        youtube,
+        "search",
+        &[
+            ("q", query),
+            ("type", "channel"),
+            ("part", "snippet"),
+            ("maxResults", &max_results),
+        ],
+        SEARCH_LIST_QUOTA_COST,
+    )
+    .await?;
+
+    let ids: Vec<&str> = json["items"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item["id"]["channelId"].as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // Search results don't include handles and subscriber counts
+    let json = youtube_api(
+        youtube,
+        "channels",
+        &[
+            ("id", &ids.join(",")),
+            ("part", "snippet,statistics"),
+            ("maxResults", &max_results),
+        ],
+        CHANNELS_LIST_QUOTA_COST,
+    )
+    .await?;
+    let items = json["items"].as_array().cloned().unwrap_or_default();
+
+    // Ordered by search relevance, channels without a handle can't be followed so they're skipped
+    let candidates = ids
+        .iter()
+        .filter_map(|id| items.iter().find(|item| item["id"] == *id))
+        .filter_map(|item| {
+            let handle = normalize_handle(item["snippet"]["customUrl"].as_str()?);
+            if !is_valid_handle(&handle) {
+                return None;
+            }
+
+            let statistics = &item["statistics"];
+            let subscriber_count = if statistics["hiddenSubscriberCount"] == true {
+                None
+            } else {
+                statistics["subscriberCount"]
+                    .as_str()
+                    .and_then(|count| count.parse().ok())
+            };
+
+            Some(ChannelCandidate {
+                handle,
+                channel_id: item["id"].as_str()?.to_string(),
+                title: item["snippet"]["title"].as_str()?.to_string(),
+                subscriber_count,
+                thumbnail_url: item["snippet"]["thumbnails"]["default"]["url"]
+                    .as_str()
+                    .map(String::from),
+            })
+        })
+        .collect();
+
+    Ok(candidates)
+}
+
+async fn youtube_api(
+    youtube: &YouTube<'_>,
+    resource: &str,
+    params: &[(&str, &str)],
+    quota_cost: u64,
+) -> Result<Value> {
+    let api_key = youtube.api_key.ok_or(MissingApiKey)?;
+
     let client = reqwest::Client::new();
 
     let mut headers = ReHeaderMap::new();
     headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
 
-    let res = client.get(format!("https://www.googleapis.com/youtube/v3/channels?key={}&forHandle={}&part=snippet,id&order=date&maxResults=1", youtube_api_key, handle)).headers(headers).send().await?;
+    let res = client
+        .get(format!("{}/youtube/v3/{}", youtube.api_host, resource))
+        .query(&[("key", api_key)])
+        .query(params)
+        .headers(headers)
+        .send()
+        .await?;
     let status = res.status();
 
+    metrics::inc_counter(
+        metrics::YOUTUBE_API_REQUESTS,
+        &[("status", status.as_str())],
+        1,
+    );
+    metrics::inc_counter(metrics::YOUTUBE_API_QUOTA_UNITS, &[], quota_cost);
+
+    // Exceeded quota is reported as 403 quotaExceeded or 429 rateLimitExceeded
+    if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS {
+        return Err(Throttled.into());
+    }
+
     if status != 200 {
         eyre::bail!("Failed to fetch data {}", status)
     }
 
-    let json: Value = res.json().await?;
-    let results = json["pageInfo"]["totalResults"].as_i64().unwrap();
+    Ok(res.json().await?)
+}
 
-    if results == 0 {
+// Fallback for when the Data API quota is used up, reads the public channel page
+async fn scrape_channel(handle: &str, host: &str) -> Result<Option<ChannelDataResponse>> {
+    let client = reqwest::Client::new();
+
+    // Skips the cookie consent page served to EU visitors
+    let res = client
+        .get(format!("{}/{}", host, handle))
+        .header(COOKIE, "CONSENT=YES+1")
+        .send()
+        .await
+        .map_err(|_| Throttled)?;
+
+    if res.status() == StatusCode::NOT_FOUND {
         return Ok(None);
     }
 
-    let channel_id = json["items"][0]["id"].as_str().unwrap();
-    let channel_name = json["items"][0]["snippet"]["title"].as_str().unwrap();
+    if res.status() != 200 {
+        return Err(Throttled.into());
+    }
+
+    let html = res.text().await.map_err(|_| Throttled)?;
+
+    let channel_id = between(
+        &html,
+        "<link rel=\"canonical\" href=\"https://www.youtube.com/channel/",
+        "\"",
+    );
+    let channel_name = between(&html, "<meta property=\"og:title\" content=\"", "\"");
+
+    match (channel_id, channel_name) {
+        (Some(channel_id), Some(channel_name)) => Ok(Some(ChannelDataResponse {
+            channel_id: channel_id.to_string(),
+            channel_name: unescape_html(channel_name),
+        })),
+        _ => Err(Throttled.into()),
+    }
+}
+
+pub(crate) fn normalize_handle(handle: &str) -> String {
+    if handle.starts_with('@') {
+        handle.to_string()
+    } else {
+        format!("@{handle}")
+    }
+}
+
+// @ followed by letters, digits, dots, underscores or dashes
+fn is_valid_handle(handle: &str) -> bool {
+    handle.strip_prefix('@').is_some_and(|name| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    })
+}
+
+fn between<'a>(text: &'a str, start: &str, end: &str) -> Option<&'a str> {
+    let from = text.find(start)? + start.len();
+    let len = text[from..].find(end)?;
+
+    Some(&text[from..from + len])
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::{Matcher, Server};
+
+    use crate::storage::tests::setup_test_db;
+
+    use super::*;
+
+    fn youtube(host: &str) -> YouTube<'_> {
+        YouTube {
+            api_key: Some("test"),
+            api_host: host,
+            page_host: host,
+        }
+    }
+
+    const CHANNEL_PAGE: &str = r#"<html><head>
+        <link rel="canonical" href="https://www.youtube.com/channel/UCNCTxLZ3EKKry-oWgLlsYsw">
+        <meta property="og:title" content="Recaps &amp; Reviews">
+        </head></html>"#;
+
+    #[tokio::test]
+    async fn test_resolve_cached_handles() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let now = Utc::now();
+
+        conn.store_channel_handle(&ChannelHandleRow {
+            handle: "@manofrecaps".to_string(),
+            channel_id: Some("UC123".to_string()),
+            channel_name: Some("Man of Recaps".to_string()),
+            fetched_at: now - Duration::days(1),
+        })
+        .await?;
+        conn.store_channel_handle(&ChannelHandleRow {
+            handle: "@missing".to_string(),
+            channel_id: None,
+            channel_name: None,
+            fetched_at: now - Duration::minutes(10),
+        })
+        .await?;
+
+        // Served from the cache without calling YouTube
+        let invalid = youtube("http://invalid");
+        let channel = resolve("@ManofRecaps", &*conn, &invalid, now)
+            .await?
+            .expect("Missing cached channel");
+        assert_eq!(channel.channel_id, "UC123");
+
+        let channel = resolve("@missing", &*conn, &invalid, now).await?;
+        assert!(channel.is_none());
+
+        // Expired entries are fetched again
+        let later = now + Duration::hours(NOT_FOUND_TTL_HOURS);
+        assert!(resolve("@missing", &*conn, &invalid, later).await.is_err());
+
+        for handle in ["@", "manofrecaps", "@a/../b", "@a?b=c", "@a b"] {
+            let err = resolve(handle, &*conn, &invalid, now).await.unwrap_err();
+            assert_eq!(err.to_string(), "Invalid channel handle");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_falls_back_to_channel_page() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
 
-    Ok(Some(json!({
-        "channel_id": channel_id,
-        "channel_name": channel_name
-    })))
+        let api = server
+            .mock("GET", "/youtube/v3/channels")
+            .match_query(Matcher::Any)
+            .with_status(403)
+            .expect(2)
+            .create_async()
+            .await;
+        let page = server
+            .mock("GET", "/@ManofRecaps")
+            .with_status(200)
+            .with_body(CHANNEL_PAGE)
+            .expect(1)
+            .create_async()
+            .await;
+        let missing_page = server
+            .mock("GET", "/@missing")
+            .with_status(404)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let channel = resolve("@ManofRecaps", &*conn, &youtube(&host), Utc::now())
+            .await?
+            .expect("Missing scraped channel");
+        assert_eq!(channel.channel_id, "UCNCTxLZ3EKKry-oWgLlsYsw");
+        assert_eq!(channel.channel_name, "Recaps & Reviews");
+
+        // Stored for later lookups
+        let row = conn
+            .get_channel_handle("@manofrecaps")
+            .await?
+            .expect("Missing cache row");
+        assert_eq!(row.channel_id.as_deref(), Some("UCNCTxLZ3EKKry-oWgLlsYsw"));
+
+        assert!(resolve("@missing", &*conn, &youtube(&host), Utc::now())
+            .await?
+            .is_none());
+        let row = conn
+            .get_channel_handle("@missing")
+            .await?
+            .expect("Missing cache row");
+        assert!(row.channel_id.is_none());
+
+        api.assert_async().await;
+        page.assert_async().await;
+        missing_page.assert_async().await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_channels() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+
+        let search = server
+            .mock("GET", "/youtube/v3/search")
+            .match_query(Matcher::UrlEncoded("q".into(), "man of recaps".into()))
+            .with_body(
+                r#"{"items": [
+                    {"id": {"channelId": "UC2"}},
+                    {"id": {"channelId": "UC1"}},
+                    {"id": {"channelId": "UC3"}}
+                ]}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let channels = server
+            .mock("GET", "/youtube/v3/channels")
+            .match_query(Matcher::UrlEncoded("id".into(), "UC2,UC1,UC3".into()))
+            .with_body(
+                r#"{"items": [
+                    {"id": "UC1", "snippet": {"title": "Man of Recaps", "customUrl": "@manofrecaps",
+                        "thumbnails": {"default": {"url": "https://yt3.ggpht.com/1"}}},
+                        "statistics": {"subscriberCount": "2000000", "hiddenSubscriberCount": false}},
+                    {"id": "UC2", "snippet": {"title": "Recaps Fan", "customUrl": "recapsfan"},
+                        "statistics": {"hiddenSubscriberCount": true}},
+                    {"id": "UC3", "snippet": {"title": "No Handle"}, "statistics": {}}
+                ]}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let now = Utc::now();
+        let candidates = search_channels("man of recaps", &*conn, &youtube(&host), now).await?;
+
+        assert_eq!(
+            candidates,
+            vec![
+                ChannelCandidate {
+                    handle: "@recapsfan".to_string(),
+                    channel_id: "UC2".to_string(),
+                    title: "Recaps Fan".to_string(),
+                    subscriber_count: None,
+                    thumbnail_url: None,
+                },
+                ChannelCandidate {
+                    handle: "@manofrecaps".to_string(),
+                    channel_id: "UC1".to_string(),
+                    title: "Man of Recaps".to_string(),
+                    subscriber_count: Some(2000000),
+                    thumbnail_url: Some("https://yt3.ggpht.com/1".to_string()),
+                },
+            ]
+        );
+
+        // Cached results don't use the API quota again
+        let cached = search_channels("man of recaps", &*conn, &youtube(&host), now).await?;
+        assert_eq!(cached, candidates);
+
+        let channel = resolve("@ManofRecaps", &*conn, &youtube(&host), now)
+            .await?
+            .expect("Missing cached channel");
+        assert_eq!(channel.channel_id, "UC1");
+
+        search.assert_async().await;
+        channels.assert_async().await;
+
+        let without_key = YouTube {
+            api_key: None,
+            ..youtube(&host)
+        };
+        let err = search_channels("other", &*conn, &without_key, now)
+            .await
+            .unwrap_err();
+        assert!(err.is::<MissingApiKey>());
+
+        assert!(search_impl(Some("  ".to_string()), &*conn).await.is_err());
+        assert!(search_impl(Some("a".repeat(101)), &*conn).await.is_err());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,28 @@
+use axum::{
+    extract::State,
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::IntoResponse,
+};
+use eyre::Result;
+
+use crate::{config::routes::AppState, metrics, storage::Storage};
+
+pub async fn show(State(state): State<AppState>) -> impl IntoResponse {
+    let gauges = match gauges(state.conn.as_ref()).await {
+        Ok(gauges) => gauges,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(&gauges),
+    )
+        .into_response()
+}
+
+async fn gauges(conn: &dyn Storage) -> Result<Vec<(&'static str, i64)>> {
+    Ok(vec![
+        (metrics::USERS, conn.count_users().await?),
+        (metrics::CHANNELS, conn.count_followed_channels().await?),
+    ])
+}
@@ -1,2 +1,4 @@
 pub mod account;
 pub mod channels;
+pub mod metrics;
+pub mod slack;
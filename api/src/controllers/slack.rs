@@ -0,0 +1,345 @@
+use axum::{body::Bytes, extract::State, http::HeaderMap, response::IntoResponse};
+use chrono::{DateTime, Duration, Utc};
+use eyre::{OptionExt, Result};
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+use uuid::Uuid;
+use yt_sub_core::{api::SlackLinkResponse, channel::Channel, UserSettings};
+
+use crate::{
+    auth::authenticate,
+    config::{
+        routes::{invalid_req, json_response, unauthorized, AppState},
+        ApiConfig,
+    },
+    controllers::channels::{normalize_handle, show_impl},
+    storage::Storage,
+    user_settings_api::UserSettingsAPI,
+};
+
+// Slack recommends rejecting requests older than 5 minutes to prevent replay attacks
+const MAX_REQUEST_AGE_SECS: i64 = 60 * 5;
+
+const USAGE: &str = "Usage: `/ytsub follow @handle`, `/ytsub unfollow @handle`, `/ytsub list` or `/ytsub link CODE`";
+
+const NOT_LINKED: &str = "This Slack channel is not linked to a ytsub account. Run `ytsub account slack-link` and enter `/ytsub link CODE` here.";
+
+const LINK_CODE_TTL_MINUTES: i64 = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct SlashCommand {
+    pub text: String,
+    pub team_id: String,
+    pub channel_id: String,
+}
+
+pub async fn command(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let Some(signing_secret) = ApiConfig::current().slack_signing_secret.as_deref() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Slack commands are not configured",
+        )
+            .into_response();
+    };
+
+    if let Err(e) = verify_signature(&headers, &body, signing_secret, Utc::now()) {
+        return (StatusCode::UNAUTHORIZED, e.to_string()).into_response();
+    }
+
+    let command: SlashCommand = match serde_urlencoded::from_bytes(&body) {
+        Ok(command) => command,
+        Err(e) => return invalid_req(&e.to_string()),
+    };
+
+    let conn = state.conn.as_ref();
+    let text = match state.rate_limits.slack_team.check(&command.team_id) {
+        Err(retry_after) => format!(
+            "Error: Too many commands, try again in {} seconds.",
+            retry_after.as_secs_f64().ceil().max(1.0)
+        ),
+        Ok(_) => match command_impl(command, Utc::now(), conn).await {
+            Ok(text) => text,
+            Err(e) => format!("Error: {e}"),
+        },
+    };
+
+    // Slack displays the error messages too, so they are returned with 200
+    json_response(
+        json!({
+            "response_type": "ephemeral",
+            "text": text,
+        }),
+        StatusCode::OK,
+    )
+}
+
+pub fn verify_signature(
+    headers: &HeaderMap,
+    body: &[u8],
+    signing_secret: &str,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let timestamp = headers
+        .get("X-Slack-Request-Timestamp")
+        .and_then(|header| header.to_str().ok())
+        .ok_or_eyre("Missing X-Slack-Request-Timestamp header")?;
+
+    let signature = headers
+        .get("X-Slack-Signature")
+        .and_then(|header| header.to_str().ok())
+        .ok_or_eyre("Missing X-Slack-Signature header")?;
+
+    let sent_at: i64 = timestamp.parse()?;
+    if (now.timestamp() - sent_at).abs() > MAX_REQUEST_AGE_SECS {
+        eyre::bail!("Slack request timestamp is too old")
+    }
+
+    let signature = signature
+        .strip_prefix("v0=")
+        .ok_or_eyre("Invalid Slack signature version")?;
+    let signature = hex::decode(signature).map_err(|_| eyre::eyre!("Invalid Slack signature"))?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_secret.as_bytes())?;
+    mac.update(format!("v0:{timestamp}:").as_bytes());
+    mac.update(body);
+
+    mac.verify_slice(&signature)
+        .map_err(|_| eyre::eyre!("Invalid Slack signature"))
+}
+
+pub async fn link_code(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let conn = state.conn.as_ref();
+    let user_id = match authenticate(&headers, conn).await {
+        Ok(user_id) => user_id,
+        Err(e) => return unauthorized(&e.to_string()),
+    };
+
+    match link_code_impl(&user_id, Utc::now(), conn).await {
+        Ok(response) => json_response(json!(response), StatusCode::OK),
+        Err(e) => invalid_req(&e.to_string()),
+    }
+}
+
+async fn link_code_impl(
+    user_id: &str,
+    now: DateTime<Utc>,
+    conn: &dyn Storage,
+) -> Result<SlackLinkResponse> {
+    let code = Uuid::new_v4().simple().to_string()[..10].to_string();
+    let expires_at = now + Duration::minutes(LINK_CODE_TTL_MINUTES);
+
+    conn.create_slack_link_code(user_id, &code, expires_at)
+        .await?;
+
+    Ok(SlackLinkResponse { code, expires_at })
+}
+
+async fn command_impl(
+    command: SlashCommand,
+    now: DateTime<Utc>,
+    conn: &dyn Storage,
+) -> Result<String> {
+    let mut args = command.text.split_whitespace();
+    let subcommand = args.next();
+
+    // Linking is the only command available before the channel is linked
+    if subcommand == Some("link") {
+        let code = args.next().ok_or_eyre(USAGE)?;
+        conn.link_slack_channel(code, &command.team_id, &command.channel_id, now)
+            .await?
+            .ok_or_eyre("Invalid or expired link code")?;
+
+        return Ok("This Slack channel is now linked to your ytsub account!".to_string());
+    }
+
+    let (user_id, settings) = find_settings(&command, conn).await?;
+
+    match subcommand {
+        Some("list") => {
+            if settings.channels.is_empty() {
+                return Ok("Currently you are not following any channels.".to_string());
+            }
+
+            let channels = settings
+                .channels
+                .iter()
+                .map(|channel| format!("• {} ({})", channel.description, channel.handle))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            Ok(format!("You are following:\n\n{channels}"))
+        }
+        Some("follow") => {
+            let handle = normalize_handle(args.next().ok_or_eyre(USAGE)?);
+
+            if let Some(following) = settings.get_channel_by_handle(&handle) {
+                eyre::bail!("You are already following {}!", following.description)
+            }
+
+            let data = show_impl(Some(handle.clone()), conn)
+                .await?
+                .ok_or_else(|| eyre::eyre!("Channel with handle '{handle}' not found!"))?;
+
+            let channel = Channel {
+                handle,
+                description: data.channel_name,
+                channel_id: data.channel_id,
+            };
+
+            if settings.get_channel_by_id(&channel.channel_id).is_some() {
+                eyre::bail!("You are already following {}!", channel.description)
+            }
+
+            let mut channels = settings.channels.clone();
+            channels.push(channel.clone());
+            UserSettings {
+                channels,
+                ..settings
+            }
+            .save(&user_id, conn)
+            .await?;
+
+            Ok(format!("You are now following {}!", channel.description))
+        }
+        Some("unfollow") => {
+            let handle = normalize_handle(args.next().ok_or_eyre(USAGE)?);
+
+            let to_unfollow = settings
+                .get_channel_by_handle(&handle)
+                .ok_or_eyre("You are not following a channel with the provided handle!")?;
+
+            let channels = settings
+                .channels
+                .iter()
+                .filter(|channel| channel.handle != handle)
+                .cloned()
+                .collect();
+            UserSettings {
+                channels,
+                ..settings
+            }
+            .save(&user_id, conn)
+            .await?;
+
+            Ok(format!("You've unfollowed {}!", to_unfollow.description))
+        }
+        _ => Ok(USAGE.to_string()),
+    }
+}
+
+// Commands only control the account linked to the channel with `/ytsub link`,
+// the team ID is verified by the request signature
+async fn find_settings(
+    command: &SlashCommand,
+    conn: &dyn Storage,
+) -> Result<(String, UserSettings)> {
+    let user_id = conn
+        .slack_link_user(&command.team_id, &command.channel_id)
+        .await?
+        .ok_or_eyre(NOT_LINKED)?;
+
+    let settings = UserSettings::read(&user_id, conn).await?;
+
+    Ok((user_id, settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use crate::{controllers::account::tests::build_settings, storage::tests::setup_test_db};
+
+    use super::*;
+
+    fn signed_headers(body: &[u8], secret: &str, timestamp: i64) -> HeaderMap {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("v0:{timestamp}:").as_bytes());
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Slack-Request-Timestamp",
+            HeaderValue::from_str(&timestamp.to_string()).unwrap(),
+        );
+        headers.insert(
+            "X-Slack-Signature",
+            HeaderValue::from_str(&format!("v0={signature}")).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let body = b"command=%2Fytsub&text=list&channel_id=C123";
+        let now = Utc::now();
+        let headers = signed_headers(body, "secret", now.timestamp());
+
+        assert!(verify_signature(&headers, body, "secret", now).is_ok());
+        assert!(verify_signature(&headers, body, "other_secret", now).is_err());
+        assert!(verify_signature(&headers, b"text=unfollow", "secret", now).is_err());
+
+        let headers = signed_headers(body, "secret", now.timestamp() - 60 * 10);
+        assert!(verify_signature(&headers, body, "secret", now).is_err());
+
+        assert!(verify_signature(&HeaderMap::new(), body, "secret", now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_unfollow_commands() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let now = Utc::now();
+
+        let mut settings = build_settings(true, Some("https://slack.com/webhook".to_string()));
+        settings.channels = vec![Channel {
+            handle: "@ManofRecaps".to_string(),
+            description: "Man of Recaps".to_string(),
+            channel_id: "UCNCTxLZ3EKKry-oWgLlsYsw".to_string(),
+        }];
+        settings.save(&settings.user_id(), &*conn).await?;
+
+        let command = |team_id: &str, text: &str| SlashCommand {
+            text: text.to_string(),
+            team_id: team_id.to_string(),
+            channel_id: "C123".to_string(),
+        };
+
+        let err = command_impl(command("T1", "list"), now, &*conn)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), NOT_LINKED);
+
+        let link = link_code_impl(&settings.user_id(), now, &*conn).await?;
+        assert!(command_impl(command("T1", "link wrong"), now, &*conn)
+            .await
+            .is_err());
+        command_impl(command("T1", &format!("link {}", link.code)), now, &*conn).await?;
+
+        let res = command_impl(command("T1", "list"), now, &*conn).await?;
+        assert!(res.contains("Man of Recaps (@ManofRecaps)"));
+
+        // Same channel ID in a different workspace
+        let err = command_impl(command("T2", "unfollow ManofRecaps"), now, &*conn)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), NOT_LINKED);
+
+        let res = command_impl(command("T1", "unfollow ManofRecaps"), now, &*conn).await?;
+        assert_eq!(res, "You've unfollowed Man of Recaps!");
+
+        let settings = UserSettings::read(&settings.user_id(), &*conn).await?;
+        assert!(settings.channels.is_empty());
+
+        let res = command_impl(command("T1", "help"), now, &*conn).await?;
+        assert_eq!(res, USAGE);
+
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
+pub mod auth;
 pub mod config;
 pub mod controllers;
-pub mod lite_helpers;
+pub mod metrics;
+pub mod rate_limit;
+pub mod storage;
 pub mod tasks;
 pub mod user_settings_api;
@@ -0,0 +1,223 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{LazyLock, Mutex},
+};
+
+pub const HTTP_REQUESTS: &str = "ytsub_http_requests_total";
+pub const HTTP_REQUEST_DURATION: &str = "ytsub_http_request_duration_seconds";
+pub const SCHEDULER_TICK_DURATION: &str = "ytsub_scheduler_tick_duration_seconds";
+pub const FEEDS_FETCHED: &str = "ytsub_feeds_fetched_total";
+pub const FEED_FETCH_ERRORS: &str = "ytsub_feed_fetch_errors_total";
+pub const NOTIFICATIONS_SENT: &str = "ytsub_notifications_sent_total";
+pub const NOTIFICATIONS_FAILED: &str = "ytsub_notifications_failed_total";
+pub const USERS: &str = "ytsub_users";
+pub const CHANNELS: &str = "ytsub_channels";
+pub const YOUTUBE_API_REQUESTS: &str = "ytsub_youtube_api_requests_total";
+pub const YOUTUBE_API_QUOTA_UNITS: &str = "ytsub_youtube_api_quota_units_total";
+
+// Name, type and help text, in the order metrics are rendered
+const DESCRIPTIONS: [(&str, &str, &str); 11] = [
+    (
+        HTTP_REQUESTS,
+        "counter",
+        "HTTP requests by route and status",
+    ),
+    (
+        HTTP_REQUEST_DURATION,
+        "histogram",
+        "HTTP request latency by route",
+    ),
+    (
+        SCHEDULER_TICK_DURATION,
+        "histogram",
+        "Duration of scheduled jobs",
+    ),
+    (FEEDS_FETCHED, "counter", "Channel RSS feeds fetched"),
+    (
+        FEED_FETCH_ERRORS,
+        "counter",
+        "Channel RSS feed fetch errors by type",
+    ),
+    (
+        NOTIFICATIONS_SENT,
+        "counter",
+        "Notifications delivered by notifier kind",
+    ),
+    (
+        NOTIFICATIONS_FAILED,
+        "counter",
+        "Failed notification deliveries by notifier kind",
+    ),
+    (USERS, "gauge", "Registered users"),
+    (CHANNELS, "gauge", "Channels followed by at least one user"),
+    (
+        YOUTUBE_API_REQUESTS,
+        "counter",
+        "YouTube Data API requests by status",
+    ),
+    (
+        YOUTUBE_API_QUOTA_UNITS,
+        "counter",
+        "YouTube Data API quota units used",
+    ),
+];
+
+const DURATION_BUCKETS: [f64; 14] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0,
+];
+
+type Key = (&'static str, Vec<(&'static str, String)>);
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    counters: BTreeMap<Key, u64>,
+    histograms: BTreeMap<Key, Histogram>,
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Mutex::default);
+
+fn key(name: &'static str, labels: &[(&'static str, &str)]) -> Key {
+    let labels = labels
+        .iter()
+        .map(|(label, value)| (*label, value.to_string()))
+        .collect();
+
+    (name, labels)
+}
+
+pub fn inc_counter(name: &'static str, labels: &[(&'static str, &str)], value: u64) {
+    let mut registry = REGISTRY.lock().expect("Metrics registry poisoned");
+    *registry.counters.entry(key(name, labels)).or_default() += value;
+}
+
+pub fn observe(name: &'static str, labels: &[(&'static str, &str)], seconds: f64) {
+    let mut registry = REGISTRY.lock().expect("Metrics registry poisoned");
+    let histogram = registry.histograms.entry(key(name, labels)).or_default();
+
+    for (bucket, le) in histogram.buckets.iter_mut().zip(DURATION_BUCKETS) {
+        if seconds <= le {
+            *bucket += 1;
+        }
+    }
+    histogram.sum += seconds;
+    histogram.count += 1;
+}
+
+// Known notifier kinds, custom notifier names are user defined so they're not used as labels
+pub fn notifier_label(notifier_name: &str) -> &str {
+    match notifier_name {
+        "Log" | "Slack" | "SlackApp" | "Telegram" => notifier_name,
+        _ => "Custom",
+    }
+}
+
+/// Renders all metrics in the Prometheus text format. Gauges are computed by the caller.
+pub fn render(gauges: &[(&'static str, i64)]) -> String {
+    let registry = REGISTRY.lock().expect("Metrics registry poisoned");
+    let mut out = String::new();
+
+    for (name, kind, help) in DESCRIPTIONS {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+
+        for (_, value) in gauges.iter().filter(|(gauge, _)| *gauge == name) {
+            let _ = writeln!(out, "{name} {value}");
+        }
+
+        for ((_, labels), value) in registry.counters.iter().filter(|((n, _), _)| *n == name) {
+            let _ = writeln!(out, "{name}{} {value}", format_labels(labels, None));
+        }
+
+        for ((_, labels), histogram) in registry.histograms.iter().filter(|((n, _), _)| *n == name)
+        {
+            for (count, le) in histogram.buckets.iter().zip(DURATION_BUCKETS) {
+                let le = le.to_string();
+                let _ = writeln!(
+                    out,
+                    "{name}_bucket{} {count}",
+                    format_labels(labels, Some(&le))
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{name}_bucket{} {}",
+                format_labels(labels, Some("+Inf")),
+                histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "{name}_sum{} {}",
+                format_labels(labels, None),
+                histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "{name}_count{} {}",
+                format_labels(labels, None),
+                histogram.count
+            );
+        }
+    }
+
+    out
+}
+
+fn format_labels(labels: &[(&'static str, String)], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = labels
+        .iter()
+        .map(|(label, value)| format!("{label}=\"{}\"", escape(value)))
+        .collect();
+
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{le}\""));
+    }
+
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        inc_counter(FEED_FETCH_ERRORS, &[("kind", "test \"quoted\"")], 2);
+        observe(SCHEDULER_TICK_DURATION, &[("job", "test_render")], 0.2);
+
+        let out = render(&[(USERS, 3)]);
+
+        assert!(out.contains("# TYPE ytsub_users gauge\nytsub_users 3\n"));
+        assert!(out.contains("ytsub_feed_fetch_errors_total{kind=\"test \\\"quoted\\\"\"} 2\n"));
+        assert!(out.contains(
+            "ytsub_scheduler_tick_duration_seconds_bucket{job=\"test_render\",le=\"0.1\"} 0\n"
+        ));
+        assert!(out.contains(
+            "ytsub_scheduler_tick_duration_seconds_bucket{job=\"test_render\",le=\"0.25\"} 1\n"
+        ));
+        assert!(out.contains(
+            "ytsub_scheduler_tick_duration_seconds_bucket{job=\"test_render\",le=\"+Inf\"} 1\n"
+        ));
+        assert!(
+            out.contains("ytsub_scheduler_tick_duration_seconds_count{job=\"test_render\"} 1\n")
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    auth,
+    config::{
+        routes::{too_many_requests, AppState},
+        ApiConfig,
+    },
+};
+
+// Idle buckets are dropped once the map grows past this size, then the least
+// recently used ones if every bucket is still in use
+const MAX_BUCKETS: usize = 10_000;
+const EVICTED_BUCKETS: usize = MAX_BUCKETS / 10;
+
+#[derive(Debug, Clone)]
+pub struct ClientIp(pub String);
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token bucket rate limiter, each key can burst up to `capacity` requests
+/// and the bucket is refilled over `period`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(capacity: u32, period: Duration) -> Self {
+        Self {
+            capacity: capacity as f64,
+            refill_per_sec: capacity as f64 / period.as_secs_f64(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token for the key, returns how long to wait if there are none left.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().expect("Rate limiter poisoned");
+
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(key) {
+            buckets.retain(|_, bucket| self.refilled(bucket, now) < self.capacity);
+        }
+
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(key) {
+            let mut accessed_at = buckets
+                .values()
+                .map(|bucket| bucket.updated_at)
+                .collect::<Vec<_>>();
+            let (_, cutoff, _) = accessed_at.select_nth_unstable(EVICTED_BUCKETS);
+            let cutoff = *cutoff;
+            buckets.retain(|_, bucket| bucket.updated_at >= cutoff);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.capacity,
+            updated_at: now,
+        });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - bucket.tokens;
+            Err(Duration::from_secs_f64(missing / self.refill_per_sec))
+        }
+    }
+
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity)
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimits {
+    pub ip: RateLimiter,
+    pub api_key: RateLimiter,
+    pub accounts: RateLimiter,
+    // Slack commands come from Slack's shared IPs, so they're limited per workspace
+    pub slack_team: RateLimiter,
+}
+
+impl RateLimits {
+    pub fn new(config: &ApiConfig) -> Self {
+        Self {
+            ip: RateLimiter::new(config.ip_rate_limit_per_minute, Duration::from_secs(60)),
+            api_key: RateLimiter::new(config.key_rate_limit_per_minute, Duration::from_secs(60)),
+            accounts: RateLimiter::new(
+                config.max_accounts_per_ip_per_day,
+                Duration::from_secs(24 * 60 * 60),
+            ),
+            slack_team: RateLimiter::new(config.key_rate_limit_per_minute, Duration::from_secs(60)),
+        }
+    }
+}
+
+// The API runs behind a proxy (see `only_ssl`), so the forwarded address is preferred.
+// Clients can send their own X-Forwarded-For, only the last hop added by the proxy is trusted.
+fn client_ip(request: &Request) -> String {
+    let forwarded = request
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.rsplit(',').next())
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty());
+
+    forwarded
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|info| info.0.ip().to_string())
+        })
+        .unwrap_or("unknown".to_string())
+}
+
+pub async fn rate_limit(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let ip = client_ip(&request);
+
+    if let Err(retry_after) = state.rate_limits.ip.check(&ip) {
+        return too_many_requests(retry_after);
+    }
+
+    if let Some(api_key) = auth::api_key(request.headers()) {
+        if let Err(retry_after) = state.rate_limits.api_key.check(&auth::user_id(api_key)) {
+            return too_many_requests(retry_after);
+        }
+    }
+
+    request.extensions_mut().insert(ClientIp(ip));
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert!(limiter.check_at("a", now).is_ok());
+        assert!(limiter.check_at("a", now).is_ok());
+        assert_eq!(limiter.check_at("a", now), Err(Duration::from_secs(30)));
+        assert!(limiter.check_at("b", now).is_ok());
+
+        let later = now + Duration::from_secs(30);
+        assert!(limiter.check_at("a", later).is_ok());
+        assert!(limiter.check_at("a", later).is_err());
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        for i in 0..MAX_BUCKETS {
+            let at = now + Duration::from_millis(i as u64);
+            assert!(limiter.check_at(&i.to_string(), at).is_ok());
+        }
+
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.check_at("new", later).is_ok());
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.len() <= MAX_BUCKETS - EVICTED_BUCKETS + 1);
+        assert!(!buckets.contains_key("0"));
+        assert!(buckets.contains_key(&(MAX_BUCKETS - 1).to_string()));
+        assert!(buckets.contains_key("new"));
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let request = |header: &str| {
+            Request::builder()
+                .header("x-forwarded-for", header)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        assert_eq!(client_ip(&request("1.1.1.1")), "1.1.1.1");
+        assert_eq!(client_ip(&request("6.6.6.6, 1.1.1.1")), "1.1.1.1");
+        assert_eq!(client_ip(&request("")), "unknown");
+    }
+}
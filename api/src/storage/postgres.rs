@@ -0,0 +1,19 @@
+use sqlx::{migrate::Migrator, PgPool};
+
+static MIGRATOR: Migrator = sqlx::migrate!("migrations/postgres");
+
+#[derive(Debug)]
+pub struct PgStorage {
+    pool: PgPool,
+}
+
+impl PgStorage {
+    pub async fn connect(db_url: &str) -> eyre::Result<Self> {
+        match PgPool::connect(db_url).await {
+            Ok(pool) => Ok(Self { pool }),
+            Err(error) => eyre::bail!("Error connecting to db: {}", error),
+        }
+    }
+}
+
+impl_storage!(PgStorage, MIGRATOR);
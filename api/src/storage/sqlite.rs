@@ -0,0 +1,19 @@
+use sqlx::{migrate::Migrator, SqlitePool};
+
+static MIGRATOR: Migrator = sqlx::migrate!("migrations/sqlite");
+
+#[derive(Debug)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    pub async fn connect(db_url: &str) -> eyre::Result<Self> {
+        match SqlitePool::connect(db_url).await {
+            Ok(pool) => Ok(Self { pool }),
+            Err(error) => eyre::bail!("Error connecting to db: {}", error),
+        }
+    }
+}
+
+impl_storage!(SqliteStorage, MIGRATOR);
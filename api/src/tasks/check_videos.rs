@@ -1,94 +1,414 @@
-use crate::{lite_helpers::sqlite_conn, user_settings_api::UserSettingsAPI};
-use chrono::{Timelike, Utc};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+
+use crate::{
+    metrics, storage::Storage, tasks::deliver_outbox::run_deliver_outbox,
+    user_settings_api::UserSettingsAPI,
+};
+use chrono::{DateTime, Duration, Utc};
 use eyre::Result;
-use yt_sub_core::UserSettings;
+use yt_sub_core::{channel::Channel, digest::PendingVideos, UserSettings};
+
+// Stored videos older than this can't be delivered to anyone anymore
+const VIDEOS_RETENTION_DAYS: i64 = 30;
+// Users are notified without the videos of failing channels once their last run is this old
+const FAILED_FETCH_MAX_DELAY_HOURS: i64 = 24;
+
+struct DueUser {
+    id: String,
+    settings: UserSettings,
+    last_run_at: DateTime<Utc>,
+    // Otherwise only notifiers with their own schedule are checked
+    schedule_due: bool,
+}
+
+pub async fn run_check_videos(conn: &dyn Storage) -> Result<()> {
+    let started = Instant::now();
+    let result = check_videos(Utc::now(), conn).await;
+    metrics::observe(
+        metrics::SCHEDULER_TICK_DURATION,
+        &[("job", "check_videos")],
+        started.elapsed().as_secs_f64(),
+    );
+    result?;
 
-pub async fn run_check_videos() -> Result<()> {
-    let conn = sqlite_conn(None).await?;
+    // Deliver enqueued notifications right away instead of waiting for the outbox job
+    run_deliver_outbox(conn).await
+}
 
-    let ids = match UserSettings::ids(&conn).await {
-        Ok(ids) => ids,
-        Err(e) => {
-            tracing::error!("Failed to list ids: {}", &e);
-            return Ok(());
+// Each followed channel feed is fetched once per tick and fanned out to its due followers
+pub async fn check_videos(now: DateTime<Utc>, conn: &dyn Storage) -> Result<()> {
+    let users = due_users(now, conn).await?;
+
+    tracing::info!("Checking videos for {} users", users.len());
+
+    let mut channels: HashMap<String, (Channel, DateTime<Utc>)> = HashMap::new();
+    for user in &users {
+        for channel in &user.settings.channels {
+            channels
+                .entry(channel.channel_id.clone())
+                .and_modify(|(_, since)| *since = (*since).min(user.last_run_at))
+                .or_insert((channel.clone(), user.last_run_at));
         }
-    };
+    }
+
+    tracing::info!("Fetching {} channel feeds", channels.len());
+
+    let mut failed = HashSet::new();
 
-    tracing::info!("Checking videos for {} users", ids.len());
+    for (channel_id, (channel, since)) in &channels {
+        metrics::inc_counter(metrics::FEEDS_FETCHED, &[], 1);
 
-    for user_id in ids {
-        match check_videos(user_id).await {
-            Ok(_) => {}
+        match channel.get_fresh_videos(*since).await {
+            Ok(videos) => conn.store_videos(&videos, now).await?,
             Err(e) => {
-                tracing::error!("Failed to check videos: {}", &e);
+                let kind = fetch_error_kind(&e);
+                metrics::inc_counter(metrics::FEED_FETCH_ERRORS, &[("kind", kind)], 1);
+                tracing::error!("Error: {}", e);
+
+                // Feeds that can't be parsed keep failing, e.g. for removed channels
+                if kind != "parse" {
+                    failed.insert(channel_id.clone());
+                }
             }
         }
     }
+
+    for user in users {
+        // The last run is kept, so videos of failed channels are picked up by a later run
+        if waits_for_channels(&user, &failed, now) {
+            tracing::warn!("Delaying notifications for user {}, feeds failed", user.id);
+            continue;
+        }
+
+        if let Err(e) = notify_user(&user, now, conn).await {
+            tracing::error!("Failed to check videos: {}", &e);
+        }
+    }
+
+    conn.prune_videos(now - Duration::days(VIDEOS_RETENTION_DAYS))
+        .await?;
+
     Ok(())
 }
 
-async fn check_videos(api_key: String) -> Result<()> {
-    let conn = sqlite_conn(None).await?;
-
-    let settings = UserSettings::read(&api_key, &conn).await?;
+fn waits_for_channels(user: &DueUser, failed: &HashSet<String>, now: DateTime<Utc>) -> bool {
+    user.last_run_at > now - Duration::hours(FAILED_FETCH_MAX_DELAY_HOURS)
+        && user
+            .settings
+            .channels
+            .iter()
+            .any(|channel| failed.contains(&channel.channel_id))
+}
 
-    if !matching_schedule(&settings) {
-        return Ok(());
+fn fetch_error_kind(error: &eyre::Report) -> &'static str {
+    match error.downcast_ref::<reqwest::Error>() {
+        Some(e) if e.is_timeout() => "timeout",
+        Some(e) if e.is_connect() => "connect",
+        Some(_) => "http",
+        // Feeds are fetched without checking the status, so missing channels fail parsing
+        None => "parse",
     }
+}
 
-    let last_run_at = settings
-        .last_run_at(&conn)
-        .await?
-        .unwrap_or(UserSettings::default_last_run_at());
-
-    let mut new_videos = vec![];
+async fn due_users(now: DateTime<Utc>, conn: &dyn Storage) -> Result<Vec<DueUser>> {
+    let mut users = vec![];
 
-    for channel in &settings.channels {
-        match channel.get_fresh_videos(last_run_at).await {
-            Ok(videos) => {
-                new_videos.extend(videos);
-            }
+    for id in UserSettings::ids(conn).await? {
+        let settings = match UserSettings::read(&id, conn).await {
+            Ok(settings) => settings,
             Err(e) => {
-                tracing::error!("Error: {}", e);
+                tracing::error!("Failed to read settings: {}", &e);
+                continue;
             }
+        };
+
+        let last_run_at = UserSettings::last_run_at(&id, conn).await?;
+        let schedule_due = settings.schedule_due(last_run_at, now);
+
+        if !schedule_due && !notifier_schedule_due(&id, &settings, now, conn).await? {
+            continue;
         }
+
+        users.push(DueUser {
+            id,
+            settings,
+            last_run_at: last_run_at.unwrap_or(UserSettings::default_last_run_at()),
+            schedule_due,
+        });
     }
 
-    if new_videos.is_empty() {
-        settings.update_last_run_at(Some(Utc::now()), &conn).await?;
+    Ok(users)
+}
+
+// Notifiers with their own schedule are not gated by the account schedule
+async fn notifier_schedule_due(
+    user_id: &str,
+    settings: &UserSettings,
+    now: DateTime<Utc>,
+    conn: &dyn Storage,
+) -> Result<bool> {
+    for (index, notifier) in settings.notifiers.iter().enumerate() {
+        if notifier.schedule().is_none() {
+            continue;
+        }
+
+        if conn
+            .load_pending_videos(user_id, index)
+            .await?
+            .is_due(notifier, now)
+        {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+async fn notify_user(user: &DueUser, now: DateTime<Utc>, conn: &dyn Storage) -> Result<()> {
+    let DueUser {
+        id: user_id,
+        settings,
+        last_run_at,
+        schedule_due,
+    } = user;
+
+    // Videos published after `now` are picked up by the next run
+    let new_videos = conn.user_videos(user_id, *last_run_at, now).await?;
+
+    let has_batched = settings.notifiers.iter().any(|n| n.is_batched());
+
+    if new_videos.is_empty() && !has_batched {
+        UserSettings::update_last_run_at(user_id, Some(now), conn).await?;
         return Ok(());
     }
 
-    for notifier in &settings.notifiers {
-        let notifications = new_videos
-            .iter()
-            .map(|video| video.notification_text(notifier))
-            .collect::<Vec<String>>();
+    for (index, notifier) in settings.notifiers.iter().enumerate() {
+        if !schedule_due && notifier.schedule().is_none() {
+            continue;
+        }
 
-        match notifier.notify(notifications, false).await {
-            Ok(_) => {}
-            Err(e) => {
-                tracing::error!("Error: {e}");
+        let mut state = if notifier.is_batched() {
+            conn.load_pending_videos(user_id, index).await?
+        } else {
+            PendingVideos::default()
+        };
+
+        let videos = if notifier.is_batched() {
+            state.collect(&new_videos, now);
+
+            if !state.is_due(notifier, now) {
+                conn.store_pending_videos(user_id, index, &state).await?;
+                continue;
             }
+
+            state.videos.clone()
+        } else {
+            new_videos.clone()
+        };
+
+        // Batched videos are kept until the notifier is resumed
+        if conn.is_suspended(user_id, index).await? {
+            if notifier.is_batched() {
+                conn.store_pending_videos(user_id, index, &state).await?;
+            }
+            continue;
+        }
+
+        if videos.is_empty() {
+            if notifier.is_batched() {
+                state.delivered(now);
+                conn.store_pending_videos(user_id, index, &state).await?;
+            }
+            continue;
+        }
+
+        // Failed deliveries are retried by the outbox worker
+        let message = notifier
+            .notifications(&videos, &settings.notifier_templates(notifier))
+            .join("\n\n");
+        conn.enqueue_outbox(user_id, index, notifier, &videos, &message)
+            .await?;
+
+        if notifier.is_batched() {
+            state.delivered(now);
+            conn.store_pending_videos(user_id, index, &state).await?;
         }
     }
 
-    settings.update_last_run_at(Some(Utc::now()), &conn).await?;
+    // Notifiers without their own schedule still need the videos since the last run
+    if *schedule_due {
+        UserSettings::update_last_run_at(user_id, Some(now), conn).await?;
+    }
 
     Ok(())
 }
 
-fn matching_schedule(settings: &UserSettings) -> bool {
-    let schedule = &settings.schedule;
+#[cfg(test)]
+mod tests {
+    use chrono::SubsecRound;
+    use yt_sub_core::{notifier::Notifier, schedule::Schedule, video::Video};
 
-    if schedule.is_none() {
-        return true;
+    use crate::{controllers::account::tests::build_settings, storage::tests::setup_test_db};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fan_out_stored_videos() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        // Postgres stores timestamps with microsecond precision
+        let now = Utc::now().trunc_subsecs(6);
+
+        let channel = Channel {
+            handle: "@ManofRecaps".to_string(),
+            description: "Man of Recaps".to_string(),
+            channel_id: "UCNCTxLZ3EKKry-oWgLlsYsw".to_string(),
+        };
+
+        let mut user_ids = vec![];
+        for _ in 0..2 {
+            let mut settings = build_settings(true, Some("https://slack.com/webhook".to_string()));
+            settings.channels = vec![channel.clone()];
+            settings.save(&settings.user_id(), &*conn).await?;
+            user_ids.push(settings.user_id());
+        }
+
+        let video = |link: &str, published_at: DateTime<Utc>| Video {
+            channel: "Man of Recaps".to_string(),
+            title: "Title".to_string(),
+            link: link.to_string(),
+            published_at,
+            thumbnail_url: None,
+            channel_handle: String::new(),
+            channel_id: channel.channel_id.clone(),
+        };
+        conn.store_videos(
+            &[
+                video(
+                    "https://www.youtube.com/watch?v=1",
+                    now - Duration::hours(1),
+                ),
+                video("https://www.youtube.com/watch?v=2", now - Duration::days(8)),
+                video(
+                    "https://www.youtube.com/watch?v=3",
+                    now + Duration::minutes(1),
+                ),
+            ],
+            now,
+        )
+        .await?;
+
+        for user in due_users(now, &*conn).await? {
+            notify_user(&user, now, &*conn).await?;
+        }
+
+        for user_id in &user_ids {
+            let deliveries = conn.outbox_history(user_id, 10).await?;
+            assert_eq!(deliveries.len(), 1);
+
+            let videos = deliveries[0].videos()?;
+            assert_eq!(videos.len(), 1);
+            assert_eq!(videos[0].link, "https://www.youtube.com/watch?v=1");
+            assert_eq!(videos[0].channel_handle, "@ManofRecaps");
+
+            assert_eq!(UserSettings::last_run_at(user_id, &*conn).await?, Some(now));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_for_failed_channels() {
+        let now = Utc::now();
+        let mut settings = build_settings(true, None);
+        settings.channels = vec![Channel {
+            handle: "@ManofRecaps".to_string(),
+            description: "Man of Recaps".to_string(),
+            channel_id: "UCNCTxLZ3EKKry-oWgLlsYsw".to_string(),
+        }];
+        let mut user = DueUser {
+            id: settings.user_id(),
+            settings,
+            last_run_at: now - Duration::hours(1),
+            schedule_due: true,
+        };
+
+        let failed = HashSet::from(["UCNCTxLZ3EKKry-oWgLlsYsw".to_string()]);
+        assert!(waits_for_channels(&user, &failed, now));
+        assert!(!waits_for_channels(&user, &HashSet::new(), now));
+
+        user.last_run_at = now - Duration::hours(FAILED_FETCH_MAX_DELAY_HOURS);
+        assert!(!waits_for_channels(&user, &failed, now));
     }
 
-    let schedule = &schedule.clone().unwrap();
-    let current_utc_hour = Utc::now().hour();
+    #[tokio::test]
+    async fn test_notifier_schedule_is_not_gated_by_account_schedule() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let morning: DateTime<Utc> = "2024-11-07T08:05:00Z".parse()?;
+        let now: DateTime<Utc> = "2024-11-07T12:10:00Z".parse()?;
+
+        let channel = Channel {
+            handle: "@ManofRecaps".to_string(),
+            description: "Man of Recaps".to_string(),
+            channel_id: "UCNCTxLZ3EKKry-oWgLlsYsw".to_string(),
+        };
+
+        let mut settings = build_settings(true, Some("https://slack.com/webhook".to_string()));
+        let Notifier::Slack(immediate) = settings.notifiers[0].clone() else {
+            unreachable!()
+        };
+        settings
+            .notifiers
+            .push(Notifier::Slack(yt_sub_core::notifier::SlackConfig {
+                schedule: Some(Schedule::Hours(vec![12])),
+                ..immediate
+            }));
+        settings.schedule = Some(Schedule::Hours(vec![8]));
+        settings.channels = vec![channel.clone()];
+        let user_id = settings.user_id();
+        settings.save(&user_id, &*conn).await?;
+        UserSettings::update_last_run_at(&user_id, Some(morning), &*conn).await?;
+
+        conn.store_videos(
+            &[Video {
+                channel: "Man of Recaps".to_string(),
+                title: "Title".to_string(),
+                link: "https://www.youtube.com/watch?v=1".to_string(),
+                published_at: now - Duration::hours(2),
+                thumbnail_url: None,
+                channel_handle: String::new(),
+                channel_id: channel.channel_id.clone(),
+            }],
+            now,
+        )
+        .await?;
 
-    schedule
-        .iter()
-        .any(|&scheduled_hour| current_utc_hour == scheduled_hour)
+        let users = due_users(now, &*conn).await?;
+        assert_eq!(users.len(), 1);
+        assert!(!users[0].schedule_due);
+        notify_user(&users[0], now, &*conn).await?;
+
+        // Only the notifier with its own schedule is delivered, the rest waits for 8:00
+        let deliveries = conn.outbox_history(&user_id, 10).await?;
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].notifier_index, 1);
+        assert_eq!(
+            UserSettings::last_run_at(&user_id, &*conn).await?,
+            Some(morning)
+        );
+
+        // The same videos are not collected again by the next account run
+        let next_morning = morning + Duration::days(1);
+        let users = due_users(next_morning, &*conn).await?;
+        assert!(users[0].schedule_due);
+        notify_user(&users[0], next_morning, &*conn).await?;
+
+        let deliveries = conn.outbox_history(&user_id, 10).await?;
+        assert_eq!(deliveries.len(), 2);
+        assert_eq!(deliveries[0].notifier_index, 0);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,199 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use eyre::Result;
+use yt_sub_core::{digest::PendingVideos, UserSettings};
+
+use crate::{
+    metrics,
+    storage::{OutboxRow, Storage, OUTBOX_DEAD},
+    user_settings_api::UserSettingsAPI,
+};
+
+pub async fn run_deliver_outbox(conn: &dyn Storage) -> Result<()> {
+    let started = Instant::now();
+    let result = deliver_outbox(Utc::now(), conn).await;
+    metrics::observe(
+        metrics::SCHEDULER_TICK_DURATION,
+        &[("job", "deliver_outbox")],
+        started.elapsed().as_secs_f64(),
+    );
+    result
+}
+
+pub async fn deliver_outbox(now: DateTime<Utc>, conn: &dyn Storage) -> Result<()> {
+    let rows = conn.claim_outbox(now).await?;
+
+    if !rows.is_empty() {
+        tracing::info!("Delivering {} outbox notifications", rows.len());
+    }
+
+    for mut row in rows {
+        let index = row.notifier_index as usize;
+
+        let res = deliver(&mut row, conn).await;
+        let notifier = [("notifier", metrics::notifier_label(&row.notifier_name))];
+
+        match res {
+            Ok(_) => {
+                metrics::inc_counter(metrics::NOTIFICATIONS_SENT, &notifier, 1);
+                conn.mark_delivered(&row, Utc::now()).await?;
+                conn.record_success(&row.user_id, index).await?;
+            }
+            Err(e) => {
+                metrics::inc_counter(metrics::NOTIFICATIONS_FAILED, &notifier, 1);
+                let status = conn.mark_failed(&row, &e.to_string(), Utc::now()).await?;
+
+                let suspended = conn
+                    .record_failure(&row.user_id, index, &e.to_string(), Utc::now())
+                    .await?;
+
+                if suspended {
+                    tracing::error!("Suspended notifier {index} of user {}", row.user_id);
+                    alert_suspended(&row, &e.to_string(), conn).await;
+                }
+
+                if status == OUTBOX_DEAD {
+                    tracing::error!("Giving up on outbox notification {}: {e}", row.id);
+                } else {
+                    tracing::error!("Failed to deliver outbox notification {}: {e}", row.id);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Progress is kept in `delivered_parts`, so a retry only posts the remaining messages
+async fn deliver(row: &mut OutboxRow, conn: &dyn Storage) -> Result<()> {
+    let settings = UserSettings::read(&row.user_id, conn).await?;
+    let index = row.notifier_index as usize;
+
+    let Some(notifier) = settings.notifiers.get(index) else {
+        eyre::bail!("Notifier {index} no longer exists");
+    };
+
+    let videos = row.videos()?;
+
+    let mut state = if notifier.is_stateful() {
+        conn.load_pending_videos(&row.user_id, index).await?
+    } else {
+        PendingVideos::default()
+    };
+    let slack_thread = state.slack_thread.clone();
+
+    let mut sent = row.delivered_parts as usize;
+    let res = notifier
+        .deliver_from(
+            &videos,
+            &mut state,
+            &settings.notifier_templates(notifier),
+            false,
+            &mut sent,
+        )
+        .await;
+    row.delivered_parts = sent as i64;
+
+    // Only the thread is updated, pending videos are managed by the check videos task
+    if state.slack_thread != slack_thread {
+        let mut current = conn.load_pending_videos(&row.user_id, index).await?;
+        current.slack_thread = state.slack_thread;
+        conn.store_pending_videos(&row.user_id, index, &current)
+            .await?;
+    }
+
+    res
+}
+
+// Suspension is reported through the remaining healthy notifiers
+async fn alert_suspended(row: &OutboxRow, error: &str, conn: &dyn Storage) {
+    let Ok(settings) = UserSettings::read(&row.user_id, conn).await else {
+        return;
+    };
+
+    let message = format!(
+        "Notifier {} ({}) was suspended after repeated delivery failures: {error}. Fix its config and run 'ytsub sync' or 'ytsub account resume' to re-enable it.",
+        row.notifier_index, row.notifier_name
+    );
+
+    for (index, notifier) in settings.notifiers.iter().enumerate() {
+        if index == row.notifier_index as usize
+            || conn.is_suspended(&row.user_id, index).await.unwrap_or(true)
+        {
+            continue;
+        }
+
+        if let Err(e) = notifier.notify(vec![message.clone()], false).await {
+            tracing::error!("Failed to send suspension alert: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Server;
+    use yt_sub_core::video::Video;
+
+    use crate::{
+        controllers::account::tests::build_settings,
+        storage::{tests::setup_test_db, OUTBOX_DELIVERED, OUTBOX_PENDING},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_deliver_outbox_with_retry() -> Result<()> {
+        let (conn, _cl) = setup_test_db().await;
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+
+        let settings = build_settings(true, Some(format!("{host}/slack_webhook")));
+        let user_id = settings.user_id();
+        settings.save(&user_id, &*conn).await?;
+
+        let video = Video {
+            channel: "Channel".to_string(),
+            title: "Title".to_string(),
+            link: "https://www.youtube.com/watch?v=1".to_string(),
+            published_at: Utc::now(),
+            thumbnail_url: None,
+            channel_handle: String::new(),
+            channel_id: String::new(),
+        };
+        let id = conn
+            .enqueue_outbox(&user_id, 0, &settings.notifiers[0], &[video], "New video")
+            .await?;
+
+        let m = server
+            .mock("POST", "/slack_webhook")
+            .with_status(500)
+            .with_body("internal_error")
+            .create_async()
+            .await;
+
+        deliver_outbox(Utc::now(), conn.as_ref()).await?;
+        m.assert_async().await;
+
+        let row = conn.get_outbox(id).await?.unwrap();
+        assert_eq!(row.status, OUTBOX_PENDING);
+        assert_eq!(row.attempts, 1);
+        assert!(row.last_error.unwrap().contains("internal_error"));
+
+        let m = server
+            .mock("POST", "/slack_webhook")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        deliver_outbox(row.next_attempt_at, &*conn).await?;
+        m.assert_async().await;
+
+        let row = conn.get_outbox(id).await?.unwrap();
+        assert_eq!(row.status, OUTBOX_DELIVERED);
+        assert_eq!(row.attempts, 2);
+        assert!(row.delivered_at.is_some());
+
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 pub mod check_videos;
+pub mod deliver_outbox;
 pub mod uptime_ping;
 
 pub use check_videos::run_check_videos;
+pub use deliver_outbox::run_deliver_outbox;
 pub use uptime_ping::run_uptime_ping;
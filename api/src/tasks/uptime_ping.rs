@@ -1,8 +1,7 @@
 use eyre::Result;
 
-pub async fn run_uptime_ping() -> Result<()> {
+pub async fn run_uptime_ping(uptime_url: &str) -> Result<()> {
     let client = reqwest::Client::new();
-    let uptime_url = std::env::var("UPTIME_URL").expect("Missing UPTIME_URL env var");
 
     let _ = client.get(uptime_url).send().await?;
 
@@ -1,78 +1,82 @@
 use chrono::{DateTime, Duration, Utc};
 use eyre::{OptionExt, Result};
-use sqlx::SqlitePool;
-use yt_sub_core::UserSettings;
+use yt_sub_core::{
+    account::{AccountStatus, NotifierStatus},
+    UserSettings,
+};
 
-use crate::lite_helpers::UserRow;
+use crate::{auth, config::ApiConfig, storage::Storage};
 
 #[allow(async_fn_in_trait)]
 pub trait UserSettingsAPI {
-    fn api_key(&self) -> String;
-    async fn read(api_key: &str, conn: &SqlitePool) -> Result<UserSettings>;
-    async fn last_run_at(&self, conn: &SqlitePool) -> Result<Option<DateTime<Utc>>>;
-    async fn update_last_run_at(self, when: Option<DateTime<Utc>>, conn: &SqlitePool)
-        -> Result<()>;
-    async fn ids(conn: &SqlitePool) -> Result<Vec<String>>;
-    async fn save(&self, conn: &SqlitePool) -> Result<()>;
-    async fn delete(api_key: &str, conn: &SqlitePool) -> Result<()>;
+    fn user_id(&self) -> String;
+    async fn read(user_id: &str, conn: &dyn Storage) -> Result<UserSettings>;
+    async fn last_run_at(user_id: &str, conn: &dyn Storage) -> Result<Option<DateTime<Utc>>>;
+    async fn update_last_run_at(
+        user_id: &str,
+        when: Option<DateTime<Utc>>,
+        conn: &dyn Storage,
+    ) -> Result<()>;
+    async fn ids(conn: &dyn Storage) -> Result<Vec<String>>;
+    async fn save(&self, user_id: &str, conn: &dyn Storage) -> Result<()>;
+    async fn delete(user_id: &str, conn: &dyn Storage) -> Result<()>;
+    async fn account_status(&self, user_id: &str, conn: &dyn Storage) -> Result<AccountStatus>;
     fn default_last_run_at() -> DateTime<Utc>;
 }
 
 impl UserSettingsAPI for UserSettings {
-    fn api_key(&self) -> String {
-        self.api_key.clone().expect("Missing API key")
+    fn user_id(&self) -> String {
+        auth::user_id(self.api_key.as_deref().expect("Missing API key"))
     }
 
-    async fn read(api_key: &str, conn: &SqlitePool) -> Result<Self> {
-        let row = UserRow::get(api_key, conn)
+    async fn read(user_id: &str, conn: &dyn Storage) -> Result<Self> {
+        let row = conn
+            .get_user(user_id)
             .await?
             .ok_or_eyre("No settings found for user")?;
 
-        let settings: UserSettings =
-            serde_json::from_str(&row.settings_json).expect("Failed to parse settings");
-
-        Ok(settings)
+        conn.user_settings(&row).await
     }
 
-    async fn delete(api_key: &str, conn: &SqlitePool) -> Result<()> {
-        let exists = UserRow::exists(api_key, conn).await?;
+    async fn delete(user_id: &str, conn: &dyn Storage) -> Result<()> {
+        let exists = conn.user_exists(user_id).await?;
 
         if !exists {
             eyre::bail!("API key not found")
         }
 
-        UserRow::delete(api_key, conn).await?;
+        conn.delete_user(user_id).await?;
 
         Ok(())
     }
 
-    async fn ids(conn: &SqlitePool) -> Result<Vec<String>> {
-        UserRow::ids(conn).await
+    async fn ids(conn: &dyn Storage) -> Result<Vec<String>> {
+        conn.user_ids().await
     }
 
-    async fn save(&self, conn: &SqlitePool) -> Result<()> {
-        if self.channels.len() > 100 {
+    async fn save(&self, user_id: &str, conn: &dyn Storage) -> Result<()> {
+        let config = ApiConfig::current();
+
+        if self.channels.len() > config.max_channels {
             eyre::bail!("Too many channels!")
         }
 
-        if self.notifiers.len() > 5 {
+        if self.notifiers.len() > config.max_notifiers {
             eyre::bail!("Too many notifiers!")
         }
 
-        let json = serde_json::to_string(&self)?;
+        self.validate_schedule()?;
+        self.validate_notifiers()?;
+        self.validate_templates()?;
 
-        let user_row = UserRow {
-            id: self.api_key(),
-            settings_json: json,
-            last_run_at: None,
-        };
-        user_row.save(conn).await?;
+        conn.save_user(user_id, self).await?;
 
         Ok(())
     }
 
-    async fn last_run_at(&self, conn: &SqlitePool) -> Result<Option<DateTime<Utc>>> {
-        let row = UserRow::get(&self.api_key(), conn)
+    async fn last_run_at(user_id: &str, conn: &dyn Storage) -> Result<Option<DateTime<Utc>>> {
+        let row = conn
+            .get_user(user_id)
             .await?
             .ok_or_eyre("No settings found for user")?;
 
@@ -83,23 +87,46 @@ impl UserSettingsAPI for UserSettings {
         Utc::now() - Duration::days(7)
     }
 
+    async fn account_status(&self, user_id: &str, conn: &dyn Storage) -> Result<AccountStatus> {
+        let health = conn.notifier_health(user_id).await?;
+
+        let notifiers = self
+            .notifiers
+            .iter()
+            .enumerate()
+            .map(|(index, notifier)| {
+                let row = health.iter().find(|h| h.notifier_index == index as i64);
+
+                NotifierStatus {
+                    index,
+                    name: notifier.name().to_string(),
+                    suspended: row.is_some_and(|h| h.suspended_at.is_some()),
+                    consecutive_failures: row.map_or(0, |h| h.consecutive_failures as u32),
+                    last_error: row.and_then(|h| h.last_error.clone()),
+                    suspended_at: row.and_then(|h| h.suspended_at),
+                }
+            })
+            .collect();
+
+        Ok(AccountStatus { notifiers })
+    }
+
     async fn update_last_run_at(
-        self,
+        user_id: &str,
         when: Option<DateTime<Utc>>,
-        conn: &SqlitePool,
+        conn: &dyn Storage,
     ) -> Result<()> {
         let last_run_at = when.unwrap_or(UserSettings::default_last_run_at());
 
-        UserRow::update_last_run_at(&self.api_key(), last_run_at, conn).await?;
+        conn.update_last_run_at(user_id, last_run_at).await?;
 
         Ok(())
     }
 }
 
 #[cfg(test)]
-
 mod tests {
-    use crate::{controllers::account::tests::build_settings, lite_helpers::tests::setup_test_db};
+    use crate::{controllers::account::tests::build_settings, storage::tests::setup_test_db};
     use UserSettingsAPI;
 
     use super::*;
@@ -108,17 +135,17 @@ mod tests {
     async fn test_add_list_users() -> Result<()> {
         let (conn, _cl) = setup_test_db().await;
 
-        let before: Vec<String> = UserSettings::ids(&conn).await?;
+        let before: Vec<String> = UserSettings::ids(&*conn).await?;
         assert_eq!(before.len(), 0);
 
         let settings = build_settings(true, None);
 
-        settings.save(&conn).await?;
+        settings.save(&settings.user_id(), &*conn).await?;
 
-        let after = UserSettings::ids(&conn).await?;
+        let after = UserSettings::ids(&*conn).await?;
 
         assert_eq!(after.len(), 1);
-        assert_eq!(after[0], settings.api_key());
+        assert_eq!(after[0], settings.user_id());
 
         Ok(())
     }
@@ -128,16 +155,15 @@ mod tests {
         let (conn, _cl) = setup_test_db().await;
 
         let settings = build_settings(true, None);
-        settings.save(&conn).await?;
-
-        assert!(settings.last_run_at(&conn).await?.is_none());
+        let user_id = settings.user_id();
+        settings.save(&user_id, &*conn).await?;
 
-        let api_key = settings.api_key();
-        settings.update_last_run_at(Some(Utc::now()), &conn).await?;
+        assert!(UserSettings::last_run_at(&user_id, &*conn).await?.is_none());
 
-        let settings = UserSettings::read(&api_key, &conn).await?;
+        UserSettings::update_last_run_at(&user_id, Some(Utc::now()), &*conn).await?;
 
-        assert!(settings.last_run_at(&conn).await?.unwrap() > Utc::now() - Duration::hours(1));
+        let last_run_at = UserSettings::last_run_at(&user_id, &*conn).await?;
+        assert!(last_run_at.unwrap() > Utc::now() - Duration::hours(1));
         Ok(())
     }
 
@@ -146,10 +172,12 @@ mod tests {
         let (conn, _cl) = setup_test_db().await;
 
         let settings = build_settings(true, None);
-        settings.save(&conn).await?;
+        let user_id = settings.user_id();
+        settings.save(&user_id, &*conn).await?;
 
-        let settings = UserSettings::read(&settings.api_key(), &conn).await?;
-        settings.save(&conn).await?;
+        let settings = UserSettings::read(&user_id, &*conn).await?;
+        assert_eq!(settings.api_key, None);
+        settings.save(&user_id, &*conn).await?;
 
         Ok(())
     }
@@ -159,14 +187,14 @@ mod tests {
         let (conn, _cl) = setup_test_db().await;
 
         let settings = build_settings(true, None);
-        settings.save(&conn).await?;
+        settings.save(&settings.user_id(), &*conn).await?;
 
-        let before = UserSettings::ids(&conn).await?;
+        let before = UserSettings::ids(&*conn).await?;
         assert_eq!(before.len(), 1);
 
-        UserSettings::delete(&settings.api_key(), &conn).await?;
+        UserSettings::delete(&settings.user_id(), &*conn).await?;
 
-        let after = UserSettings::ids(&conn).await?;
+        let after = UserSettings::ids(&*conn).await?;
         assert_eq!(after.len(), 0);
 
         Ok(())
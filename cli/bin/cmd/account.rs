@@ -0,0 +1,198 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use eyre::Result;
+use serde_json::json;
+use yt_sub::{
+    output::{print_json, OutputFormat},
+    user_settings_cli::UserSettingsCLI,
+};
+use yt_sub_core::{
+    account::{Account, AccountStatus},
+    UserSettings,
+};
+
+use crate::CONFIG_DESC;
+
+#[derive(Debug, Parser)]
+pub struct AccountArgs {
+    #[command(subcommand)]
+    cmd: AccountSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum AccountSubcommand {
+    #[command(about = "Display remote account status and differences with the local config")]
+    Status(StatusArgs),
+    #[command(about = "Re-enable notifiers suspended after delivery failures")]
+    Resume(ResumeArgs),
+    #[command(about = "Replace the remote account API key with a new one")]
+    RotateKey(RotateKeyArgs),
+    #[command(about = "Get a one-time code for controlling the account from a Slack channel")]
+    SlackLink(SlackLinkArgs),
+}
+
+#[derive(Debug, Parser)]
+struct StatusArgs {
+    #[arg(long, help = CONFIG_DESC)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+struct ResumeArgs {
+    #[arg(long, help = CONFIG_DESC)]
+    config: Option<PathBuf>,
+
+    #[arg(long, help = "Only resume the notifier at a given index")]
+    index: Option<usize>,
+}
+
+#[derive(Debug, Parser)]
+struct RotateKeyArgs {
+    #[arg(long, help = CONFIG_DESC)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+struct SlackLinkArgs {
+    #[arg(long, help = CONFIG_DESC)]
+    config: Option<PathBuf>,
+}
+
+impl AccountArgs {
+    pub async fn run(self, output: OutputFormat) -> Result<()> {
+        match self.cmd {
+            AccountSubcommand::Status(args) => {
+                let settings = UserSettings::read(args.config.as_ref())?;
+                let account = settings.account(None).await?;
+                print_account(&settings, &account, output);
+            }
+            AccountSubcommand::Resume(args) => {
+                let settings = UserSettings::read(args.config.as_ref())?;
+                let status = settings.resume_notifiers(args.index, None).await?;
+                print_status(&status, output);
+            }
+            AccountSubcommand::RotateKey(args) => {
+                let settings = UserSettings::read(args.config.as_ref())?;
+                let path = settings.path.clone();
+                settings.rotate_api_key(None).await?;
+
+                if output.is_json() {
+                    print_json("account_rotate_key", json!({ "config": path }));
+                } else {
+                    println!(
+                        "API key was rotated and saved to {}. The previous key no longer works.",
+                        path.display()
+                    );
+                }
+            }
+            AccountSubcommand::SlackLink(args) => {
+                let settings = UserSettings::read(args.config.as_ref())?;
+                let link = settings.slack_link(None).await?;
+
+                if output.is_json() {
+                    print_json("account_slack_link", json!(link));
+                } else {
+                    println!(
+                        "Enter '/ytsub link {}' in the Slack channel that should control this account. The code expires at {}.",
+                        link.code,
+                        link.expires_at.format("%Y-%m-%d %H:%M UTC")
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn print_account(settings: &UserSettings, account: &Account, output: OutputFormat) {
+    let diff = settings.diff(&account.settings);
+
+    if output.is_json() {
+        print_json(
+            "account",
+            json!({
+                "account": account,
+                "diff": diff,
+            }),
+        );
+        return;
+    }
+
+    let format_date = |date: Option<DateTime<Utc>>| {
+        date.map_or("never".to_string(), |date| {
+            date.format("%Y-%m-%d %H:%M UTC").to_string()
+        })
+    };
+
+    println!("Last run: {}", format_date(account.last_run_at));
+    println!("Next check: {}", format_date(account.next_check_at));
+
+    println!("\nNotifiers:");
+    print_notifiers(&AccountStatus {
+        notifiers: account.notifiers.clone(),
+    });
+
+    if !account.deliveries.is_empty() {
+        println!("\nRecent deliveries:");
+        for delivery in &account.deliveries {
+            let error = match (&delivery.status[..], &delivery.last_error) {
+                ("delivered", _) | (_, None) => String::new(),
+                (_, Some(error)) => format!(" - {error}"),
+            };
+            println!(
+                "{} [{}] {}: {} ({} attempts){error}",
+                format_date(Some(delivery.created_at)),
+                delivery.notifier_index,
+                delivery.notifier_name,
+                delivery.status,
+                delivery.attempts,
+            );
+        }
+    }
+
+    println!("\nRemote settings:");
+    if diff.is_empty() {
+        println!("In sync with the local config.");
+    } else {
+        println!("{diff}\n\nRun 'ytsub sync' to update the remote settings.");
+    }
+}
+
+fn print_status(status: &AccountStatus, output: OutputFormat) {
+    if output.is_json() {
+        print_json("account_status", status);
+        return;
+    }
+
+    print_notifiers(status);
+}
+
+fn print_notifiers(status: &AccountStatus) {
+    for notifier in &status.notifiers {
+        if notifier.suspended {
+            println!(
+                "[{}] {}: suspended - {}",
+                notifier.index,
+                notifier.name,
+                notifier.last_error.as_deref().unwrap_or("unknown error")
+            );
+        } else if notifier.consecutive_failures > 0 {
+            println!(
+                "[{}] {}: failing ({} failures) - {}",
+                notifier.index,
+                notifier.name,
+                notifier.consecutive_failures,
+                notifier.last_error.as_deref().unwrap_or("unknown error")
+            );
+        } else {
+            println!("[{}] {}: active", notifier.index, notifier.name);
+        }
+    }
+
+    if !status.suspended().is_empty() {
+        println!("\nFix the notifiers config and run 'ytsub sync' or 'ytsub account resume' to re-enable them.");
+    }
+}
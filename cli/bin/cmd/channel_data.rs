@@ -1,5 +1,6 @@
 use clap::Parser;
 use eyre::Result;
+use yt_sub::output::{print_json, OutputFormat};
 use yt_sub_core::channel::Channel;
 
 #[derive(Debug, Parser)]
@@ -9,7 +10,7 @@ pub struct ChannelDataArgs {
 }
 
 impl ChannelDataArgs {
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(self, output: OutputFormat) -> Result<()> {
         let Self { handle } = self;
         let (channel_id, channel_name) = Channel::get_data(&handle, None).await?;
 
@@ -19,6 +20,11 @@ impl ChannelDataArgs {
             channel_id: channel_id.clone(),
         };
 
+        if output.is_json() {
+            print_json("channel", &channel);
+            return Ok(());
+        }
+
         println!(
             "{channel}
 
@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use clap::Parser;
+use eyre::Result;
+use yt_sub::{output::OutputFormat, user_settings_cli::UserSettingsCLI};
+use yt_sub_core::{logger::Logger, UserSettings};
+
+use crate::{cmd::run::RunArgs, CONFIG_DESC};
+
+#[derive(Debug, Parser)]
+pub struct DaemonArgs {
+    #[arg(long, help = CONFIG_DESC)]
+    config: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 60, help = "Check interval in minutes")]
+    interval: u64,
+}
+
+impl DaemonArgs {
+    pub async fn run(self) -> Result<()> {
+        let Self { config, interval } = self;
+
+        if interval == 0 {
+            eyre::bail!("--interval must be greater than 0");
+        }
+
+        let logger = Logger::new(true);
+        let path = config.clone().unwrap_or_else(UserSettings::default_path);
+
+        // Fail fast if the config file is missing or invalid
+        UserSettings::read(Some(&path))?.validate_schedule()?;
+
+        logger.info(&format!(
+            "Daemon started, checking every {interval} minutes using {}",
+            path.display()
+        ));
+
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval * 60));
+        let shutdown = shutdown_signal();
+        tokio::pin!(shutdown);
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => {
+                    logger.info("Shutting down daemon");
+                    break;
+                }
+                _ = ticker.tick() => {
+                    // Config is re-read on every tick, so edits apply from the next check
+                    let settings = match UserSettings::read(Some(&path)) {
+                        Ok(settings) => settings,
+                        Err(e) => {
+                            logger.error(&format!("Error: {e}"));
+                            continue;
+                        }
+                    };
+
+                    if !settings.schedule_due(Some(settings.last_run_at()), Utc::now()) {
+                        continue;
+                    }
+
+                    match RunArgs::scheduled(Some(path.clone()))
+                        .run(OutputFormat::Text)
+                        .await
+                    {
+                        Ok(_) => {}
+                        Err(e) => {
+                            logger.error(&format!("Error: {e}"));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigterm = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+
+        tokio::select! {
+            _ = sigterm.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
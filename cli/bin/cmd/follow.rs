@@ -2,7 +2,11 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use eyre::Result;
-use yt_sub::user_settings_cli::UserSettingsCLI;
+use serde_json::json;
+use yt_sub::{
+    output::{print_json, OutputFormat},
+    user_settings_cli::UserSettingsCLI,
+};
 use yt_sub_core::{channel::Channel, UserSettings};
 
 use crate::CONFIG_DESC;
@@ -21,7 +25,16 @@ pub struct FollowArgs {
 }
 
 impl FollowArgs {
-    pub async fn run(self) -> Result<()> {
+    pub fn for_channel(config: Option<PathBuf>, channel: Channel) -> Self {
+        Self {
+            config,
+            handle: channel.handle,
+            channel_id: Some(channel.channel_id),
+            desc: Some(channel.description),
+        }
+    }
+
+    pub async fn run(self, output: OutputFormat) -> Result<()> {
         let Self {
             channel_id,
             desc,
@@ -70,16 +83,23 @@ impl FollowArgs {
 
         settings.save(config.as_ref())?;
 
-        println!(
-            "You are now following:
+        if !output.is_json() {
+            println!(
+                "You are now following:
 
 {channel}"
-        );
+            );
+        }
+
+        let mut remote_synced = false;
 
         if settings.api_key.is_some() {
             match settings.sync_account(None).await {
                 Ok(_) => {
-                    println!("Remote account data was updated.");
+                    remote_synced = true;
+                    if !output.is_json() {
+                        println!("Remote account data was updated.");
+                    }
                 }
                 Err(e) => {
                     eprintln!("Error: {}", e)
@@ -87,6 +107,16 @@ impl FollowArgs {
             }
         }
 
+        if output.is_json() {
+            print_json(
+                "follow",
+                json!({
+                    "channel": channel,
+                    "remote_synced": remote_synced,
+                }),
+            );
+        }
+
         Ok(())
     }
 }
@@ -2,7 +2,10 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use eyre::Result;
-use yt_sub::user_settings_cli::UserSettingsCLI;
+use yt_sub::{
+    output::{print_json, OutputFormat},
+    user_settings_cli::UserSettingsCLI,
+};
 use yt_sub_core::UserSettings;
 
 use crate::CONFIG_DESC;
@@ -17,12 +20,17 @@ pub struct ListArgs {
 }
 
 impl ListArgs {
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(self, output: OutputFormat) -> Result<()> {
         let Self { config } = self;
 
         let settings = UserSettings::read(config.as_ref())?;
         let channels = settings.channels;
 
+        if output.is_json() {
+            print_json("channels", &channels);
+            return Ok(());
+        }
+
         if channels.is_empty() {
             println!("Currently you are not following any channels.");
             return Ok(());
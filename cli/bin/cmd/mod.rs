@@ -1,9 +1,14 @@
+pub mod account;
 pub mod channel_data;
+pub mod daemon;
 pub mod follow;
 pub mod init;
 pub mod list;
+pub mod notifiers;
+pub mod pull;
 pub mod register;
 pub mod run;
+pub mod search;
 pub mod settings;
 pub mod sync;
 pub mod unfollow;
@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use eyre::Result;
+use serde_json::json;
+use yt_sub::{
+    output::{print_json, CliError, OutputFormat},
+    user_settings_cli::UserSettingsCLI,
+};
+use yt_sub_core::UserSettings;
+
+use crate::CONFIG_DESC;
+
+const TEST_MESSAGE: &str = "Test message from yt-sub-rs. This notifier is configured correctly.";
+
+#[derive(Debug, Parser)]
+pub struct NotifiersArgs {
+    #[command(subcommand)]
+    cmd: NotifiersSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum NotifiersSubcommand {
+    #[command(about = "Validate notifiers and send a test message through each of them")]
+    Test(TestArgs),
+}
+
+#[derive(Debug, Parser)]
+struct TestArgs {
+    #[arg(long, help = CONFIG_DESC)]
+    config: Option<PathBuf>,
+
+    #[arg(long, help = "Only test the notifier at a given index")]
+    index: Option<usize>,
+
+    #[arg(long, help = "Check notifiers config without sending messages")]
+    validate_only: bool,
+}
+
+impl NotifiersArgs {
+    pub async fn run(self, output: OutputFormat) -> Result<()> {
+        match self.cmd {
+            NotifiersSubcommand::Test(args) => args.run(output).await,
+        }
+    }
+}
+
+impl TestArgs {
+    async fn run(self, output: OutputFormat) -> Result<()> {
+        let Self {
+            config,
+            index,
+            validate_only,
+        } = self;
+
+        // Invalid notifiers are reported individually instead of failing at load
+        let settings = UserSettings::load(config.as_ref())?;
+
+        if let Some(index) = index {
+            if index >= settings.notifiers.len() {
+                eyre::bail!(
+                    "Invalid notifier index {index}, {} notifiers configured",
+                    settings.notifiers.len()
+                );
+            }
+        }
+
+        let mut results = vec![];
+        let mut invalid = vec![];
+        let mut failed = vec![];
+
+        for (i, notifier) in settings.notifiers.iter().enumerate() {
+            if index.is_some_and(|index| index != i) {
+                continue;
+            }
+
+            // Log output would break the JSON document
+            let send = !(validate_only || output.is_json() && notifier.is_log());
+
+            let res = match notifier.validate() {
+                Err(e) => {
+                    invalid.push(format!("[{i}] {}: {e}", notifier.name()));
+                    Err(e)
+                }
+                Ok(_) if send => notifier.verify(TEST_MESSAGE).await.inspect_err(|e| {
+                    failed.push(format!("[{i}] {}: {e}", notifier.name()));
+                }),
+                Ok(_) => Ok(()),
+            };
+
+            let status = match (&res, send) {
+                (Err(_), _) => "failed",
+                (Ok(_), true) => "sent",
+                (Ok(_), false) => "valid",
+            };
+
+            if !output.is_json() {
+                match &res {
+                    Ok(_) => println!("[{i}] {}: {status}", notifier.name()),
+                    Err(e) => println!("[{i}] {}: {status} - {e}", notifier.name()),
+                }
+            }
+
+            results.push(json!({
+                "index": i,
+                "notifier": notifier.name(),
+                "status": status,
+                "error": res.err().map(|e| e.to_string()),
+            }));
+        }
+
+        // Invalid config takes precedence, the notifiers can't work until it's fixed
+        if !invalid.is_empty() {
+            return Err(CliError::Config(format!(
+                "Invalid notifiers config:\n  {}",
+                invalid.join("\n  ")
+            ))
+            .into());
+        }
+
+        if !failed.is_empty() {
+            return Err(CliError::Remote(format!(
+                "Failed to send test messages:\n  {}",
+                failed.join("\n  ")
+            ))
+            .into());
+        }
+
+        if output.is_json() {
+            print_json(
+                "notifiers_test",
+                json!({
+                    "validate_only": validate_only,
+                    "notifiers": results,
+                }),
+            );
+        }
+
+        Ok(())
+    }
+}
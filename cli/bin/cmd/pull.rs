@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use eyre::Result;
+use serde_json::json;
+use yt_sub::{
+    output::{print_json, CliError, OutputFormat},
+    user_settings_cli::UserSettingsCLI,
+};
+use yt_sub_core::{merge::MergeSide, UserSettings};
+
+use crate::CONFIG_DESC;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Prefer {
+    Local,
+    Remote,
+}
+
+#[derive(Debug, Parser)]
+pub struct PullArgs {
+    #[arg(long, help = CONFIG_DESC)]
+    config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Resolve conflicting changes using local or remote values"
+    )]
+    prefer: Option<Prefer>,
+
+    #[arg(long, help = "Display changes without updating the config file")]
+    dry_run: bool,
+}
+
+impl PullArgs {
+    pub async fn run(self, output: OutputFormat) -> Result<()> {
+        let Self {
+            config,
+            prefer,
+            dry_run,
+        } = self;
+
+        let settings = UserSettings::read(config.as_ref())?;
+        let remote = settings.account(None).await?.settings;
+        let base = settings.synced_settings()?;
+
+        let diff = settings.diff(&remote);
+        let side = match prefer {
+            Some(Prefer::Remote) => MergeSide::Remote,
+            Some(Prefer::Local) | None => MergeSide::Local,
+        };
+        let (merged, conflicts) = settings.merge(&remote, base.as_ref(), side);
+
+        // Conflicts are reported as an error, so JSON output only includes a single document
+        if !conflicts.is_empty() && prefer.is_none() {
+            if !output.is_json() && !diff.is_empty() {
+                println!("{diff}\n");
+            }
+
+            let conflicts = conflicts
+                .iter()
+                .map(|c| format!("  {c}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            return Err(CliError::Config(format!(
+                "Local and remote settings were both changed:\n{conflicts}\nRerun with '--prefer local' or '--prefer remote'."
+            ))
+            .into());
+        }
+
+        if output.is_json() {
+            print_json(
+                "pull",
+                json!({
+                    "dry_run": dry_run,
+                    "diff": diff,
+                    "conflicts": conflicts,
+                    "settings": merged,
+                }),
+            );
+        } else if diff.is_empty() {
+            println!("Local config is up to date.");
+        } else {
+            println!("{diff}\n");
+        }
+
+        if dry_run {
+            return Ok(());
+        }
+
+        if diff.is_empty() {
+            settings.save_synced_settings(&remote)?;
+            return Ok(());
+        }
+
+        merged.validate_notifiers()?;
+        merged.validate_templates()?;
+        merged.save(Some(&settings.path))?;
+        settings.save_synced_settings(&remote)?;
+
+        if !output.is_json() {
+            println!("Local config was updated.");
+
+            if !merged.diff(&remote).is_empty() {
+                println!("Run 'ytsub sync' to push the remaining local changes.");
+            }
+        }
+
+        Ok(())
+    }
+}
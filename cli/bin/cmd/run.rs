@@ -1,12 +1,19 @@
 use chrono::Utc;
 use clap::Parser;
 use eyre::Result;
+use serde_json::json;
 use std::path::PathBuf;
-use yt_sub::user_settings_cli::UserSettingsCLI;
-use yt_sub_core::{logger::Logger, UserSettings};
+use yt_sub::{
+    output::{print_json, OutputFormat},
+    user_settings_cli::UserSettingsCLI,
+};
+use yt_sub_core::{digest::PendingVideos, logger::Logger, UserSettings};
 
 use crate::CONFIG_DESC;
 
+// Notifications skip the videos of failing channels once the last run is this old
+const FAILED_FETCH_MAX_DELAY_HOURS: i64 = 24;
+
 #[derive(Debug, Parser)]
 pub struct RunArgs {
     #[arg(long, help = CONFIG_DESC)]
@@ -17,14 +24,30 @@ pub struct RunArgs {
 
     #[arg(long, help = "Fresh videos hours offset")]
     hours_offset: Option<u16>,
+
+    #[arg(
+        long,
+        help = "Print notifications payloads without sending them or updating the last run time"
+    )]
+    dry_run: bool,
 }
 
 impl RunArgs {
-    pub async fn run(self) -> Result<()> {
+    pub fn scheduled(config: Option<PathBuf>) -> Self {
+        Self {
+            config,
+            cron: true,
+            hours_offset: None,
+            dry_run: false,
+        }
+    }
+
+    pub async fn run(self, output: OutputFormat) -> Result<()> {
         let Self {
             config,
             cron,
             hours_offset,
+            dry_run,
         } = self;
 
         let logger = Logger::new(cron);
@@ -38,6 +61,7 @@ impl RunArgs {
         };
 
         let mut new_videos = vec![];
+        let mut fetch_failed = false;
 
         for channel in &settings.channels {
             match channel.get_fresh_videos(last_run_at).await {
@@ -46,29 +70,186 @@ impl RunArgs {
                 }
                 Err(e) => {
                     logger.error(&format!("Error: {e}"));
+                    // Feeds that can't be parsed keep failing, e.g. for removed channels
+                    fetch_failed |= e.is::<reqwest::Error>();
                 }
             }
         }
 
-        if new_videos.is_empty() {
-            logger.info("No new videos found.");
+        // The last run time is kept, so the next run (or daemon tick) fetches the same videos again.
+        // Notifying now would repeat the videos of the other channels then.
+        if fetch_failed
+            && last_run_at > Utc::now() - chrono::Duration::hours(FAILED_FETCH_MAX_DELAY_HOURS)
+        {
+            if output.is_json() {
+                print_json(
+                    "run",
+                    json!({
+                        "dry_run": dry_run,
+                        "delayed": true,
+                        "videos": [],
+                        "notifiers": [],
+                    }),
+                );
+            } else {
+                logger.info(
+                    "Failed to fetch channel feeds, notifications are delayed until the next run.",
+                );
+            }
+            return Ok(());
+        }
+
+        let has_batched = settings.notifiers.iter().any(|n| n.is_batched());
+
+        if new_videos.is_empty() && !has_batched {
+            if output.is_json() {
+                print_json(
+                    "run",
+                    json!({
+                        "dry_run": dry_run,
+                        "videos": new_videos,
+                        "notifiers": [],
+                    }),
+                );
+            } else {
+                logger.info("No new videos found.");
+            }
+
+            if !dry_run {
+                settings.touch_last_run_at()?;
+            }
             return Ok(());
         }
 
-        for notifier in &settings.notifiers {
-            let notifications = new_videos
-                .iter()
-                .map(|video| video.notification_text(notifier))
-                .collect::<Vec<String>>();
+        if new_videos.is_empty() && !output.is_json() {
+            logger.info("No new videos found.");
+        }
+
+        let now = Utc::now();
+        let mut results = vec![];
+
+        for (index, notifier) in settings.notifiers.iter().enumerate() {
+            let mut state = if notifier.is_stateful() {
+                settings.pending_videos(index)?
+            } else {
+                PendingVideos::default()
+            };
+
+            let videos = if notifier.is_batched() {
+                state.push(&new_videos);
+
+                if !state.is_due(notifier, now) {
+                    if !dry_run {
+                        settings.save_pending_videos(index, &state)?;
+                    }
+
+                    if output.is_json() {
+                        results.push(json!({
+                            "notifier": notifier.name(),
+                            "status": "pending",
+                            "pending_videos": state.videos.len(),
+                        }));
+                    } else if !state.videos.is_empty() {
+                        logger.info(&format!(
+                            "{} videos pending for the {} notifier.",
+                            state.videos.len(),
+                            notifier.name()
+                        ));
+                    }
+                    continue;
+                }
+
+                state.videos.clone()
+            } else {
+                new_videos.clone()
+            };
+
+            if videos.is_empty() {
+                if notifier.is_batched() && !dry_run {
+                    state.delivered(now);
+                    settings.save_pending_videos(index, &state)?;
+                }
+                continue;
+            }
+
+            if dry_run {
+                let payload = notifier.preview(&videos, &settings.notifier_templates(notifier));
+
+                if output.is_json() {
+                    results.push(json!({
+                        "notifier": notifier.name(),
+                        "status": "dry_run",
+                        "payload": payload,
+                    }));
+                } else {
+                    println!(
+                        "[dry-run] {name} notifier payload:\n\n{payload}\n",
+                        name = notifier.name(),
+                    );
+                }
+                continue;
+            }
+
+            // Videos are already included in the JSON document
+            if output.is_json() && notifier.is_log() {
+                results.push(json!({
+                    "notifier": notifier.name(),
+                    "status": "skipped",
+                }));
+                continue;
+            }
 
-            match notifier.notify(notifications, cron).await {
-                Ok(_) => {}
+            let res = notifier
+                .deliver(
+                    &videos,
+                    &mut state,
+                    &settings.notifier_templates(notifier),
+                    cron,
+                )
+                .await;
+
+            // Failed deliveries keep the pending videos for the next run
+            if res.is_ok() && notifier.is_batched() {
+                state.delivered(now);
+            }
+
+            if notifier.is_stateful() {
+                settings.save_pending_videos(index, &state)?;
+            }
+
+            match res {
+                Ok(_) => {
+                    results.push(json!({
+                        "notifier": notifier.name(),
+                        "status": "sent",
+                    }));
+                }
                 Err(e) => {
                     logger.error(&format!("Error: {e}"));
+                    results.push(json!({
+                        "notifier": notifier.name(),
+                        "status": "failed",
+                        "error": e.to_string(),
+                    }));
                 }
             }
         }
 
+        if output.is_json() {
+            print_json(
+                "run",
+                json!({
+                    "dry_run": dry_run,
+                    "videos": new_videos,
+                    "notifiers": results,
+                }),
+            );
+        }
+
+        if dry_run {
+            return Ok(());
+        }
+
         settings.touch_last_run_at()?;
 
         Ok(())
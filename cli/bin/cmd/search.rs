@@ -0,0 +1,77 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use eyre::Result;
+use yt_sub::output::{print_json, OutputFormat};
+use yt_sub_core::channel::Channel;
+
+use crate::{cmd::follow::FollowArgs, CONFIG_DESC};
+
+#[derive(Debug, Parser)]
+pub struct SearchArgs {
+    #[arg(long, help = CONFIG_DESC)]
+    config: Option<PathBuf>,
+
+    #[arg(required = true, help = "Channel name or keywords")]
+    query: Vec<String>,
+}
+
+impl SearchArgs {
+    pub async fn run(self, output: OutputFormat) -> Result<()> {
+        let Self { config, query } = self;
+        let query = query.join(" ");
+        let candidates = Channel::search(&query, None).await?;
+
+        if output.is_json() {
+            print_json("channel_search", &candidates);
+            return Ok(());
+        }
+
+        if candidates.is_empty() {
+            println!("No channels found for '{query}'.");
+            return Ok(());
+        }
+
+        for (index, candidate) in candidates.iter().enumerate() {
+            println!("{}. {candidate}", index + 1);
+        }
+
+        // Scripts get the list only, following is offered in interactive shells
+        if !io::stdin().is_terminal() {
+            println!(
+                "
+Run:
+
+ytsub follow --handle <handle>
+
+to subscribe to a channel."
+            );
+            return Ok(());
+        }
+
+        print!("\nEnter a number to follow a channel, or press Enter to skip: ");
+        io::stdout().flush()?;
+
+        let mut selection = String::new();
+        io::stdin().read_line(&mut selection)?;
+        let selection = selection.trim();
+
+        if selection.is_empty() {
+            return Ok(());
+        }
+
+        let candidate = selection
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| candidates.get(number.wrapping_sub(1)))
+            .ok_or_else(|| eyre::eyre!("Invalid selection '{selection}'"))?;
+
+        println!();
+        FollowArgs::for_channel(config, candidate.to_channel())
+            .run(output)
+            .await
+    }
+}
@@ -1,7 +1,11 @@
 use clap::Parser;
 use eyre::Result;
+use serde_json::json;
 use std::path::PathBuf;
-use yt_sub::user_settings_cli::UserSettingsCLI;
+use yt_sub::{
+    output::{print_json, OutputFormat},
+    user_settings_cli::UserSettingsCLI,
+};
 use yt_sub_core::UserSettings;
 
 use crate::CONFIG_DESC;
@@ -13,10 +17,22 @@ pub struct SettingsArgs {
 }
 
 impl SettingsArgs {
-    pub fn run(self) -> Result<()> {
+    pub fn run(self, output: OutputFormat) -> Result<()> {
         let Self { config } = self;
 
         let settings = UserSettings::read(config.as_ref())?;
+
+        if output.is_json() {
+            print_json(
+                "settings",
+                json!({
+                    "path": settings.path,
+                    "settings": settings,
+                }),
+            );
+            return Ok(());
+        }
+
         println!("{settings}");
         Ok(())
     }
@@ -19,8 +19,20 @@ impl SyncArgs {
 
         let settings = UserSettings::read(config.as_ref())?;
         settings.sync_account(None).await?;
+        settings.save_synced_settings(&settings)?;
         println!("Remote account data was updated.");
 
+        // Notifiers with changed config are re-enabled by the sync
+        let status = settings.account_status(None).await?;
+        for notifier in status.suspended() {
+            println!(
+                "Warning: notifier {} ({}) is suspended - {}. Run 'ytsub account resume' once it's fixed.",
+                notifier.index,
+                notifier.name,
+                notifier.last_error.as_deref().unwrap_or("unknown error")
+            );
+        }
+
         Ok(())
     }
 }
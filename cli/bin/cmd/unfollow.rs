@@ -2,7 +2,11 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use eyre::Result;
-use yt_sub::user_settings_cli::UserSettingsCLI;
+use serde_json::json;
+use yt_sub::{
+    output::{print_json, OutputFormat},
+    user_settings_cli::UserSettingsCLI,
+};
 use yt_sub_core::UserSettings;
 
 use crate::CONFIG_DESC;
@@ -17,7 +21,7 @@ pub struct UnfollowArgs {
 }
 
 impl UnfollowArgs {
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(self, output: OutputFormat) -> Result<()> {
         let Self { handle, config } = self;
 
         let settings = UserSettings::read(config.as_ref())?;
@@ -42,12 +46,19 @@ impl UnfollowArgs {
 
         settings.save(config.as_ref())?;
 
-        println!("You've unfollowed {desc}!", desc = to_unfollow.description);
+        if !output.is_json() {
+            println!("You've unfollowed {desc}!", desc = to_unfollow.description);
+        }
+
+        let mut remote_synced = false;
 
         if settings.api_key.is_some() {
             match settings.sync_account(None).await {
                 Ok(_) => {
-                    println!("Remote account data was updated.");
+                    remote_synced = true;
+                    if !output.is_json() {
+                        println!("Remote account data was updated.");
+                    }
                 }
                 Err(e) => {
                     eprintln!("Error: {}", e)
@@ -55,6 +66,16 @@ impl UnfollowArgs {
             }
         }
 
+        if output.is_json() {
+            print_json(
+                "unfollow",
+                json!({
+                    "channel": to_unfollow,
+                    "remote_synced": remote_synced,
+                }),
+            );
+        }
+
         Ok(())
     }
 }
@@ -1,17 +1,28 @@
 use clap::{Parser, Subcommand};
 mod cmd;
 use cmd::{
-    channel_data::ChannelDataArgs, follow::FollowArgs, init::InitArgs, list::ListArgs,
-    register::RegisterArgs, run::RunArgs, settings::SettingsArgs, sync::SyncArgs,
-    unfollow::UnfollowArgs, unregister::UnregisterArgs,
+    account::AccountArgs, channel_data::ChannelDataArgs, daemon::DaemonArgs, follow::FollowArgs,
+    init::InitArgs, list::ListArgs, notifiers::NotifiersArgs, pull::PullArgs,
+    register::RegisterArgs, run::RunArgs, search::SearchArgs, settings::SettingsArgs,
+    sync::SyncArgs, unfollow::UnfollowArgs, unregister::UnregisterArgs,
 };
 use eyre::Result;
+use yt_sub::output::{print_json, ErrorCategory, OutputFormat};
 
 pub static CONFIG_DESC: &str = "Path to config file, deafult '~/.config/yt-sub-rs/config.toml'";
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct SubArgs {
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format"
+    )]
+    pub output: OutputFormat,
+
     #[command(subcommand)]
     pub cmd: SubSubcommand,
 }
@@ -26,6 +37,8 @@ pub enum SubSubcommand {
     Run(RunArgs),
     #[command(visible_alias = "d", about = "Get a channel data based on its handle")]
     ChannelData(ChannelDataArgs),
+    #[command(about = "Search channels by name and follow one")]
+    Search(SearchArgs),
     #[command(visible_alias = "f", about = "Subscribe to a channel")]
     Follow(FollowArgs),
     #[command(visible_alias = "u", about = "Unsubscribe")]
@@ -38,29 +51,57 @@ pub enum SubSubcommand {
     Unregister(UnregisterArgs),
     #[command(visible_alias = "sc", about = "Update remote settings to match local")]
     Sync(SyncArgs),
+    #[command(about = "Periodically check and notify about fresh videos")]
+    Daemon(DaemonArgs),
+    #[command(visible_alias = "n", about = "Manage configured notifiers")]
+    Notifiers(NotifiersArgs),
+    #[command(visible_alias = "a", about = "Manage remote account")]
+    Account(AccountArgs),
+    #[command(
+        visible_alias = "p",
+        about = "Update local config to match remote settings"
+    )]
+    Pull(PullArgs),
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = SubArgs::parse();
+    let output = args.output;
     let res = match args.cmd {
         SubSubcommand::Init(args) => args.run(),
-        SubSubcommand::Settings(args) => args.run(),
-        SubSubcommand::Run(args) => args.run().await,
-        SubSubcommand::ChannelData(args) => args.run().await,
-        SubSubcommand::Follow(args) => args.run().await,
-        SubSubcommand::Unfollow(args) => args.run().await,
-        SubSubcommand::List(args) => args.run().await,
+        SubSubcommand::Settings(args) => args.run(output),
+        SubSubcommand::Run(args) => args.run(output).await,
+        SubSubcommand::ChannelData(args) => args.run(output).await,
+        SubSubcommand::Search(args) => args.run(output).await,
+        SubSubcommand::Follow(args) => args.run(output).await,
+        SubSubcommand::Unfollow(args) => args.run(output).await,
+        SubSubcommand::List(args) => args.run(output).await,
         SubSubcommand::Register(args) => args.run().await,
         SubSubcommand::Unregister(args) => args.run().await,
         SubSubcommand::Sync(args) => args.run().await,
+        SubSubcommand::Daemon(args) => args.run().await,
+        SubSubcommand::Notifiers(args) => args.run(output).await,
+        SubSubcommand::Account(args) => args.run(output).await,
+        SubSubcommand::Pull(args) => args.run(output).await,
     };
 
-    match res {
-        Ok(_) => {}
-        Err(e) => {
+    if let Err(e) = res {
+        let category = ErrorCategory::from_report(&e);
+
+        if output.is_json() {
+            print_json(
+                "error",
+                serde_json::json!({
+                    "category": category,
+                    "message": e.to_string(),
+                }),
+            );
+        } else {
             eprintln!("Error: {}", e)
         }
+
+        std::process::exit(category.exit_code());
     };
 
     Ok(())
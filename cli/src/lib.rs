@@ -1,2 +1,3 @@
+pub mod output;
 pub mod test_helpers;
 pub mod user_settings_cli;
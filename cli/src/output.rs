@@ -0,0 +1,125 @@
+use std::fmt::{self, Display, Formatter};
+
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn is_json(&self) -> bool {
+        self == &Self::Json
+    }
+}
+
+pub fn json_document(kind: &str, data: impl Serialize) -> Value {
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "kind": kind,
+        "data": data,
+    })
+}
+
+pub fn print_json(kind: &str, data: impl Serialize) {
+    let doc = json_document(kind, data);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&doc).expect("Failed to serialize JSON")
+    );
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    Config(String),
+    Remote(String),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Config(msg) | CliError::Remote(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    General,
+    Config,
+    Network,
+    Remote,
+}
+
+impl ErrorCategory {
+    pub fn from_report(err: &eyre::Report) -> Self {
+        for cause in err.chain() {
+            if let Some(cli_err) = cause.downcast_ref::<CliError>() {
+                return match cli_err {
+                    CliError::Config(_) => Self::Config,
+                    CliError::Remote(_) => Self::Remote,
+                };
+            }
+
+            if cause.is::<reqwest::Error>() {
+                return Self::Network;
+            }
+
+            if cause.is::<toml::de::Error>() || cause.is::<std::io::Error>() {
+                return Self::Config;
+            }
+        }
+
+        Self::General
+    }
+
+    // Exit code 2 is reserved for invalid arguments reported by clap
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::General => 1,
+            Self::Config => 3,
+            Self::Network => 4,
+            Self::Remote => 5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_document() {
+        let doc = json_document("channels", vec!["a", "b"]);
+
+        assert_eq!(doc["schema_version"], SCHEMA_VERSION);
+        assert_eq!(doc["kind"], "channels");
+        assert_eq!(doc["data"], json!(["a", "b"]));
+    }
+
+    #[test]
+    fn test_error_category() {
+        let err = eyre::Report::new(CliError::Config("Missing file".to_string()));
+        assert_eq!(ErrorCategory::from_report(&err), ErrorCategory::Config);
+        assert_eq!(ErrorCategory::from_report(&err).exit_code(), 3);
+
+        let err = eyre::Report::new(CliError::Remote("Failed".to_string()));
+        assert_eq!(ErrorCategory::from_report(&err), ErrorCategory::Remote);
+
+        let err = eyre::Report::new(std::io::Error::other("Failed to read"));
+        assert_eq!(ErrorCategory::from_report(&err), ErrorCategory::Config);
+
+        let err = eyre::eyre!("Something else");
+        assert_eq!(ErrorCategory::from_report(&err), ErrorCategory::General);
+        assert_eq!(ErrorCategory::from_report(&err).exit_code(), 1);
+    }
+}
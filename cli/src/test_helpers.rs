@@ -6,7 +6,7 @@ use std::{
 use home::home_dir;
 use uuid::Uuid;
 
-use crate::user_settings_cli::UserSettingsCLI;
+use crate::user_settings_cli::{state_dir, UserSettingsCLI};
 use yt_sub_core::UserSettings;
 
 pub fn test_config_path() -> PathBuf {
@@ -28,6 +28,12 @@ pub struct Cleaner {
 
 impl Drop for Cleaner {
     fn drop(&mut self) {
+        // The default config shares its state dir with other files
+        let state_dir = state_dir(&self.path);
+        if self.path != UserSettings::default_path() && state_dir.exists() {
+            fs::remove_dir_all(state_dir).expect("Failed to remove state dir");
+        }
+
         if !Path::new(&self.path).exists() {
             return;
         }
@@ -1,6 +1,4 @@
 use eyre::{OptionExt, Result};
-use reqwest::Client;
-use serde_json::Value;
 use std::{
     fs::File,
     io::Write,
@@ -9,24 +7,47 @@ use std::{
 
 use chrono::{DateTime, Duration, Utc};
 use home::home_dir;
-use yt_sub_core::{user_settings::API_HOST, UserSettings};
+use sha2::{Digest, Sha256};
+use yt_sub_core::{
+    account::{Account, AccountStatus},
+    api::{ApiClient, ApiError, SlackLinkResponse},
+    digest::PendingVideos,
+    notifier::Notifier,
+    UserSettings,
+};
+
+use crate::output::CliError;
 
 #[allow(async_fn_in_trait)]
 pub trait UserSettingsCLI {
     fn last_run_at(&self) -> DateTime<Utc>;
     fn touch_last_run_at(&self) -> Result<()>;
+    fn pending_videos(&self, notifier_index: usize) -> Result<PendingVideos>;
+    fn save_pending_videos(&self, notifier_index: usize, pending: &PendingVideos) -> Result<()>;
+    fn synced_settings(&self) -> Result<Option<UserSettings>>;
+    fn save_synced_settings(&self, synced: &UserSettings) -> Result<()>;
     fn init(path: Option<&PathBuf>) -> Result<UserSettings>;
+    fn load(path: Option<&PathBuf>) -> Result<UserSettings>;
     fn read(path: Option<&PathBuf>) -> Result<UserSettings>;
     fn save(&self, path: Option<&PathBuf>) -> Result<()>;
     fn default_path() -> PathBuf;
     async fn create_account(self, host: Option<&str>) -> Result<()>;
     async fn delete_account(&self, host: Option<&str>) -> Result<()>;
+    async fn rotate_api_key(self, host: Option<&str>) -> Result<()>;
     async fn sync_account(&self, host: Option<&str>) -> Result<()>;
+    async fn account(&self, host: Option<&str>) -> Result<Account>;
+    async fn account_status(&self, host: Option<&str>) -> Result<AccountStatus>;
+    async fn resume_notifiers(
+        &self,
+        notifier_index: Option<usize>,
+        host: Option<&str>,
+    ) -> Result<AccountStatus>;
+    async fn slack_link(&self, host: Option<&str>) -> Result<SlackLinkResponse>;
 }
 
 impl UserSettingsCLI for UserSettings {
     fn last_run_at(&self) -> DateTime<Utc> {
-        let path = home_dir().unwrap().join(".yt-sub-rs/last_run_at.txt");
+        let path = last_run_at_path(&self.path);
         if Path::new(&path).exists() {
             let last_run_at =
                 std::fs::read_to_string(&path).expect("Failed to read last_run_at file");
@@ -39,7 +60,7 @@ impl UserSettingsCLI for UserSettings {
     }
 
     fn touch_last_run_at(&self) -> Result<()> {
-        let last_run_at_path = last_run_at_path();
+        let last_run_at_path = last_run_at_path(&self.path);
         let last_run_at = Utc::now().to_rfc3339();
         if let Some(parent) = Path::new(&last_run_at_path).parent() {
             std::fs::create_dir_all(parent)?;
@@ -49,14 +70,96 @@ impl UserSettingsCLI for UserSettings {
         Ok(())
     }
 
+    // State is keyed by the notifier config, so it stays with its notifier when the list changes
+    fn pending_videos(&self, notifier_index: usize) -> Result<PendingVideos> {
+        let path = pending_videos_path(
+            &self.path,
+            self.notifiers
+                .get(notifier_index)
+                .ok_or_eyre("Invalid notifier index")?,
+        )?;
+        if !Path::new(&path).exists() {
+            return Ok(PendingVideos::default());
+        }
+
+        let pending = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        Ok(pending)
+    }
+
+    fn save_pending_videos(&self, notifier_index: usize, pending: &PendingVideos) -> Result<()> {
+        let path = pending_videos_path(
+            &self.path,
+            self.notifiers
+                .get(notifier_index)
+                .ok_or_eyre("Invalid notifier index")?,
+        )?;
+        if let Some(parent) = Path::new(&path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(&path)?;
+        file.write_all(serde_json::to_string(pending)?.as_bytes())?;
+
+        // Files of removed or changed notifiers
+        let current = self
+            .notifiers
+            .iter()
+            .map(|notifier| pending_videos_path(&self.path, notifier))
+            .collect::<Result<Vec<_>>>()?;
+        for entry in std::fs::read_dir(state_dir(&self.path))? {
+            let stale = entry?.path();
+            let is_pending = stale
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("pending_videos_"));
+
+            if is_pending && !current.contains(&stale) {
+                std::fs::remove_file(stale)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Remote settings from the last sync or pull, used as a base for merging
+    fn synced_settings(&self) -> Result<Option<UserSettings>> {
+        let path = synced_settings_path(&self.path);
+        if !Path::new(&path).exists() {
+            return Ok(None);
+        }
+
+        let synced: UserSettings = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+
+        // Base of a different remote account is ignored
+        if synced.api_key != self.api_key {
+            return Ok(None);
+        }
+
+        Ok(Some(synced))
+    }
+
+    fn save_synced_settings(&self, synced: &UserSettings) -> Result<()> {
+        let path = synced_settings_path(&self.path);
+        if let Some(parent) = Path::new(&path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Remote settings don't include the API key, so the local one is stored to match the account
+        let mut synced = serde_json::to_value(synced)?;
+        synced["api_key"] = serde_json::json!(self.api_key);
+
+        let mut file = File::create(path)?;
+        file.write_all(synced.to_string().as_bytes())?;
+        Ok(())
+    }
+
     fn init(path: Option<&PathBuf>) -> Result<Self> {
         let default_path = Self::default_path();
         let path = path.unwrap_or(&default_path);
         if Path::new(path).exists() {
-            eyre::bail!(
+            return Err(CliError::Config(format!(
                 "Config file at '{}' is already initialized!",
                 path.display()
-            );
+            ))
+            .into());
         }
 
         let settings = Self::default(path.clone());
@@ -65,21 +168,36 @@ impl UserSettingsCLI for UserSettings {
         Ok(settings)
     }
 
-    fn read(path: Option<&PathBuf>) -> Result<Self> {
+    // Parses the config file without validating notifiers and templates
+    fn load(path: Option<&PathBuf>) -> Result<Self> {
         let default_path = Self::default_path();
         let path = path.unwrap_or(&default_path);
 
         if !Path::new(path).exists() {
-            eyre::bail!(
+            return Err(CliError::Config(format!(
                 "Config file at '{}' does not exist! Run 'ytsub init' to initialize it.",
                 path.display()
-            )
+            ))
+            .into());
         }
         let mut settings: Self = toml::from_str(&std::fs::read_to_string(path)?)?;
         settings.path = path.clone();
         Ok(settings)
     }
 
+    fn read(path: Option<&PathBuf>) -> Result<Self> {
+        let settings = Self::load(path)?;
+
+        // Invalid notifiers and templates fail at load rather than in the middle of a run
+        settings
+            .validate_notifiers()
+            .and_then(|_| settings.validate_templates())
+            .map_err(|e| {
+                CliError::Config(format!("Invalid config '{}': {e}", settings.path.display()))
+            })?;
+        Ok(settings)
+    }
+
     fn save(&self, path: Option<&PathBuf>) -> Result<()> {
         let res = toml::to_string(self).expect("Failed to serialize TOML");
 
@@ -109,23 +227,13 @@ impl UserSettingsCLI for UserSettings {
             "You must configure a Slack notifier to register a remote account:
 https://github.com/pawurb/yt-sub-rs#notifiers-configuration",
         )?;
+        self.validate_schedule()?;
 
-        let client = Client::new();
-        let host = host.unwrap_or(API_HOST);
-
-        let res = client
-            .post(format!("{}/account", host))
-            .json(&self)
-            .send()
-            .await?;
-
-        if res.status() != 201 {
-            let err_msg = res.text().await?;
-            eyre::bail!("Failed to register remote account: {err_msg}")
-        }
-
-        let res_json: Value = res.json().await?;
-        let remote_api_key = res_json["api_key"].as_str().unwrap().to_string();
+        let remote_api_key = ApiClient::new(host)
+            .create_account(&self)
+            .await
+            .map_err(|e| remote_error("Failed to register remote account", e))?
+            .api_key;
 
         let config_path = self.path.clone();
 
@@ -144,62 +252,153 @@ https://github.com/pawurb/yt-sub-rs#notifiers-configuration",
             eyre::bail!("Remote account is not registered.")
         }
 
-        let client = Client::new();
-        let host = host.unwrap_or(API_HOST);
+        ApiClient::new(host)
+            .with_api_key(self.api_key.as_deref().unwrap())
+            .delete_account()
+            .await
+            .map_err(|e| remote_error("Failed to delete remote account", e))
+    }
+    async fn rotate_api_key(self, host: Option<&str>) -> Result<()> {
+        let Some(api_key) = &self.api_key else {
+            eyre::bail!("Remote account is not registered!")
+        };
+
+        let remote_api_key = ApiClient::new(host)
+            .with_api_key(api_key)
+            .rotate_key()
+            .await
+            .map_err(|e| remote_error("Failed to rotate API key", e))?
+            .api_key;
 
-        let res = client
-            .delete(format!("{}/account", host))
-            .header("X-API-KEY", self.api_key.clone().unwrap())
-            .send()
-            .await?;
+        let synced = self.synced_settings()?;
+        let config_path = self.path.clone();
+
+        let settings = Self {
+            api_key: Some(remote_api_key),
+            ..self
+        };
+
+        settings.save(Some(&config_path))?;
 
-        if !res.status().is_success() {
-            let err_msg = res.text().await?;
-            eyre::bail!("Failed to delete remote account: {err_msg}")
+        if let Some(synced) = synced {
+            settings.save_synced_settings(&synced)?;
         }
 
         Ok(())
     }
+
     async fn sync_account(&self, host: Option<&str>) -> Result<()> {
-        if self.api_key.is_none() {
+        let Some(api_key) = &self.api_key else {
             eyre::bail!("Remote account is not registered!")
-        }
+        };
 
         _ = self.get_slack_notifier().ok_or_eyre(
             "You must configure a Slack notifier to update a remote account:
 https://github.com/pawurb/yt-sub-rs#notifiers-configuration",
         )?;
-        let client = Client::new();
-        let host = host.unwrap_or(API_HOST);
-
-        let res = client
-            .put(format!("{}/account", host))
-            .json(&self)
-            .send()
-            .await?;
-
-        if res.status() != 200 {
-            let err_msg = res.text().await?;
-            eyre::bail!("Failed to update remote account: {err_msg}")
-        }
+        self.validate_schedule()?;
 
-        Ok(())
+        ApiClient::new(host)
+            .with_api_key(api_key)
+            .update_account(self)
+            .await
+            .map_err(|e| remote_error("Failed to update remote account", e))
+    }
+
+    async fn account(&self, host: Option<&str>) -> Result<Account> {
+        let Some(api_key) = &self.api_key else {
+            eyre::bail!("Remote account is not registered!")
+        };
+
+        ApiClient::new(host)
+            .with_api_key(api_key)
+            .account()
+            .await
+            .map_err(|e| remote_error("Failed to get remote account", e))
+    }
+
+    async fn account_status(&self, host: Option<&str>) -> Result<AccountStatus> {
+        let Some(api_key) = &self.api_key else {
+            eyre::bail!("Remote account is not registered!")
+        };
+
+        ApiClient::new(host)
+            .with_api_key(api_key)
+            .account_status()
+            .await
+            .map_err(|e| remote_error("Failed to get remote account status", e))
+    }
+
+    async fn resume_notifiers(
+        &self,
+        notifier_index: Option<usize>,
+        host: Option<&str>,
+    ) -> Result<AccountStatus> {
+        let Some(api_key) = &self.api_key else {
+            eyre::bail!("Remote account is not registered!")
+        };
+
+        ApiClient::new(host)
+            .with_api_key(api_key)
+            .resume_notifiers(notifier_index)
+            .await
+            .map_err(|e| remote_error("Failed to resume notifiers", e))
+    }
+
+    async fn slack_link(&self, host: Option<&str>) -> Result<SlackLinkResponse> {
+        let Some(api_key) = &self.api_key else {
+            eyre::bail!("Remote account is not registered!")
+        };
+
+        ApiClient::new(host)
+            .with_api_key(api_key)
+            .slack_link()
+            .await
+            .map_err(|e| remote_error("Failed to create Slack link code", e))
+    }
+}
+
+// API error responses are reported as remote errors, network errors are passed through
+fn remote_error(context: &str, err: eyre::Report) -> eyre::Report {
+    match err.downcast_ref::<ApiError>() {
+        Some(api_err) => CliError::Remote(format!("{context}: {api_err}")).into(),
+        None => err,
     }
 }
 
-fn last_run_at_path() -> PathBuf {
-    home_dir().unwrap().join(".yt-sub-rs/last_run_at.txt")
+// State files are kept per config file, so separate configs don't share the last run time
+pub(crate) fn state_dir(config_path: &Path) -> PathBuf {
+    let root = home_dir().unwrap().join(".yt-sub-rs");
+
+    // The default config keeps using the files created before configs had separate state
+    if config_path == UserSettings::default_path() {
+        return root;
+    }
+
+    let config_path = std::path::absolute(config_path).unwrap_or(config_path.to_path_buf());
+    let hash = hex::encode(Sha256::digest(config_path.to_string_lossy().as_bytes()));
+    root.join(format!("config-{}", &hash[..16]))
+}
+
+fn last_run_at_path(config_path: &Path) -> PathBuf {
+    state_dir(config_path).join("last_run_at.txt")
+}
+
+fn synced_settings_path(config_path: &Path) -> PathBuf {
+    state_dir(config_path).join("synced_settings.json")
+}
+
+fn pending_videos_path(config_path: &Path, notifier: &Notifier) -> Result<PathBuf> {
+    let hash = hex::encode(Sha256::digest(serde_json::to_string(notifier)?.as_bytes()));
+    Ok(state_dir(config_path).join(format!("pending_videos_{}.json", &hash[..16])))
 }
 
 #[cfg(test)]
 mod tests {
     use mockito::Server;
-    use yt_sub_core::{
-        channel::Channel,
-        notifier::{Notifier, SlackConfig},
-    };
+    use yt_sub_core::{channel::Channel, notifier::SlackConfig, video::Video};
 
-    use crate::test_helpers::{test_config_path, Cleaner};
+    use crate::test_helpers::{init_test_settings, test_config_path, Cleaner};
 
     use super::*;
 
@@ -214,6 +413,59 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_state_per_config() -> Result<()> {
+        let (settings, _cl) = init_test_settings();
+        let (other, _cl2) = init_test_settings();
+
+        assert_eq!(
+            state_dir(&UserSettings::default_path()),
+            home_dir().unwrap().join(".yt-sub-rs")
+        );
+        assert_ne!(state_dir(&settings.path), state_dir(&other.path));
+
+        settings.touch_last_run_at()?;
+        assert!(last_run_at_path(&settings.path).exists());
+        assert!(!last_run_at_path(&other.path).exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pending_videos_follow_notifiers() -> Result<()> {
+        let (mut settings, _cl) = init_test_settings();
+        let first = build_settings(None, Some("https://slack.com/first".to_string()), None);
+        let second = build_settings(None, Some("https://slack.com/second".to_string()), None);
+        settings.notifiers = vec![first.notifiers[0].clone(), second.notifiers[0].clone()];
+
+        let mut first_state = PendingVideos::default();
+        first_state.delivered(Utc::now());
+        settings.save_pending_videos(0, &first_state)?;
+
+        let mut second_state = PendingVideos::default();
+        second_state.push(&[Video {
+            channel: "Channel".to_string(),
+            title: "Title".to_string(),
+            link: "https://www.youtube.com/watch?v=1".to_string(),
+            published_at: Utc::now(),
+            thumbnail_url: None,
+            channel_handle: String::new(),
+            channel_id: String::new(),
+        }]);
+        settings.save_pending_videos(1, &second_state)?;
+
+        settings.notifiers.remove(0);
+        assert_eq!(settings.pending_videos(0)?, second_state);
+
+        // State of the removed notifier is pruned
+        settings.save_pending_videos(0, &second_state)?;
+        settings.notifiers.insert(0, first.notifiers[0].clone());
+        assert_eq!(settings.pending_videos(0)?, PendingVideos::default());
+        assert_eq!(settings.pending_videos(1)?, second_state);
+
+        Ok(())
+    }
+
     #[tokio::test]
     #[should_panic]
     async fn test_init_twice() {
@@ -287,6 +539,38 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rotate_api_key_ok() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let host = server.host_with_port();
+        let host = format!("http://{}", host);
+
+        let path = test_config_path();
+        let _cl = Cleaner { path: path.clone() };
+
+        let settings = build_settings(
+            Some(path.clone()),
+            Some("https://slack.com/XXX".to_string()),
+            Some("test".to_string()),
+        );
+
+        let m = server
+            .mock("POST", "/account/rotate_key")
+            .match_header("X-API-KEY", "test")
+            .with_body(r#"{"api_key": "ROTATED_API_KEY" }"#)
+            .with_status(200)
+            .create_async()
+            .await;
+
+        settings.rotate_api_key(Some(&host)).await?;
+        m.assert_async().await;
+
+        let settings = UserSettings::read(Some(&path))?;
+        assert_eq!(settings.api_key, Some("ROTATED_API_KEY".to_string()));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_create_account_ok() -> Result<()> {
         let mut server = Server::new_async().await;
@@ -343,6 +627,69 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_remote_account() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+
+        let settings = build_settings(
+            None,
+            Some("https://slack.com/XXX".to_string()),
+            Some("test".into()),
+        );
+
+        let body = serde_json::json!({
+            "settings": settings,
+            "last_run_at": "2024-11-07T08:00:00Z",
+            "next_check_at": "2024-11-07T09:00:00Z",
+            "notifiers": [],
+            "deliveries": [],
+        });
+        let m = server
+            .mock("GET", "/account")
+            .match_header("X-API-KEY", "test")
+            .with_body(body.to_string())
+            .create_async()
+            .await;
+
+        let account = settings.account(Some(&host)).await?;
+        assert!(settings.diff(&account.settings).is_empty());
+        assert_eq!(account.next_check_at, Some("2024-11-07T09:00:00Z".parse()?));
+
+        m.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_account_status() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+        let m = server
+            .mock("GET", "/account/status")
+            .match_header("X-API-KEY", "test")
+            .with_body(
+                r#"{"notifiers": [{"index": 0, "name": "Slack", "suspended": true, "consecutive_failures": 10, "last_error": "invalid_token", "suspended_at": "2024-11-07T08:00:00Z"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let settings = build_settings(
+            None,
+            Some("https://slack.com/XXX".to_string()),
+            Some("test".into()),
+        );
+
+        let status = settings.account_status(Some(&host)).await?;
+        assert_eq!(status.suspended().len(), 1);
+        assert_eq!(
+            status.notifiers[0].last_error,
+            Some("invalid_token".to_string())
+        );
+
+        m.assert_async().await;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_sync_account_ok() -> Result<()> {
         let mut server = Server::new_async().await;
@@ -350,6 +697,7 @@ mod tests {
         let host = format!("http://{}", host);
         let m = server
             .mock("PUT", "/account")
+            .match_header("X-API-KEY", "test")
             .with_status(200)
             .create_async()
             .await;
@@ -379,6 +727,10 @@ mod tests {
             let notifier = Notifier::Slack(SlackConfig {
                 webhook_url: webhook,
                 channel: "test".to_string(),
+                schedule: None,
+                digest: false,
+                blocks: false,
+                templates: Default::default(),
             });
 
             settings.notifiers = vec![notifier];
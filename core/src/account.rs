@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::UserSettings;
+
+// Remote account state reported by the API
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct AccountStatus {
+    pub notifiers: Vec<NotifierStatus>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct NotifierStatus {
+    pub index: usize,
+    pub name: String,
+    pub suspended: bool,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub suspended_at: Option<DateTime<Utc>>,
+}
+
+impl AccountStatus {
+    pub fn suspended(&self) -> Vec<&NotifierStatus> {
+        self.notifiers.iter().filter(|n| n.suspended).collect()
+    }
+}
+
+// Remote account details returned by `GET /account`
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct Account {
+    pub settings: UserSettings,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub next_check_at: Option<DateTime<Utc>>,
+    pub notifiers: Vec<NotifierStatus>,
+    pub deliveries: Vec<Delivery>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Delivery {
+    pub id: i64,
+    pub notifier_index: usize,
+    pub notifier_name: String,
+    pub message: String,
+    pub status: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,295 @@
+use std::fmt::{self, Display, Formatter};
+
+use chrono::{DateTime, Utc};
+use eyre::{OptionExt, Result};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    account::{Account, AccountStatus, Delivery},
+    channel::{Channel, ChannelCandidate},
+    notifier::Notifier,
+    schedule::Schedule,
+    templates::Templates,
+    user_settings::API_HOST,
+    UserSettings,
+};
+
+// Request and response bodies shared by the API server and the client
+
+// Account settings without the API key, it's only sent in the X-API-KEY header
+#[derive(Debug, Serialize)]
+pub struct SettingsRequest<'a> {
+    pub channels: &'a [Channel],
+    pub notifiers: &'a [Notifier],
+    pub schedule: &'a Option<Schedule>,
+    pub templates: &'a Templates,
+}
+
+impl<'a> From<&'a UserSettings> for SettingsRequest<'a> {
+    fn from(settings: &'a UserSettings) -> Self {
+        Self {
+            channels: &settings.channels,
+            notifiers: &settings.notifiers,
+            schedule: &settings.schedule,
+            templates: &settings.templates,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ApiKeyResponse {
+    pub api_key: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ChannelDataResponse {
+    pub channel_id: String,
+    pub channel_name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct ResumeRequest {
+    pub notifier_index: Option<usize>,
+}
+
+// Entered as `/ytsub link <code>` in the Slack channel that should control the account
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct SlackLinkResponse {
+    pub code: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct DeliveriesResponse {
+    pub deliveries: Vec<Delivery>,
+}
+
+/// Non-success response returned by the API, the message is the response body.
+#[derive(Debug, PartialEq)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn is_not_found(&self) -> bool {
+        self.status == StatusCode::NOT_FOUND
+    }
+
+    // YouTube Data API quota is used up
+    pub fn is_throttled(&self) -> bool {
+        self.status == StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Typed client for the remote API, see `/openapi.json` for the endpoint docs.
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    host: String,
+    api_key: Option<String>,
+    client: Client,
+}
+
+impl ApiClient {
+    pub fn new(host: Option<&str>) -> Self {
+        Self {
+            host: host.unwrap_or(API_HOST).to_string(),
+            api_key: None,
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_api_key(self, api_key: &str) -> Self {
+        Self {
+            api_key: Some(api_key.to_string()),
+            ..self
+        }
+    }
+
+    pub async fn create_account(&self, settings: &UserSettings) -> Result<ApiKeyResponse> {
+        let req = self
+            .client
+            .post(self.url("/account"))
+            .json(&SettingsRequest::from(settings));
+        json(self.send(req).await?).await
+    }
+
+    pub async fn account(&self) -> Result<Account> {
+        let req = self.client.get(self.url("/account"));
+        json(self.send(self.authed(req)?).await?).await
+    }
+
+    pub async fn update_account(&self, settings: &UserSettings) -> Result<()> {
+        let req = self
+            .client
+            .put(self.url("/account"))
+            .json(&SettingsRequest::from(settings));
+        self.send(self.authed(req)?).await?;
+        Ok(())
+    }
+
+    pub async fn delete_account(&self) -> Result<()> {
+        let req = self.client.delete(self.url("/account"));
+        self.send(self.authed(req)?).await?;
+        Ok(())
+    }
+
+    pub async fn account_status(&self) -> Result<AccountStatus> {
+        let req = self.client.get(self.url("/account/status"));
+        json(self.send(self.authed(req)?).await?).await
+    }
+
+    pub async fn resume_notifiers(&self, notifier_index: Option<usize>) -> Result<AccountStatus> {
+        let req = self
+            .client
+            .post(self.url("/account/resume"))
+            .json(&ResumeRequest { notifier_index });
+        json(self.send(self.authed(req)?).await?).await
+    }
+
+    pub async fn rotate_key(&self) -> Result<ApiKeyResponse> {
+        let req = self.client.post(self.url("/account/rotate_key"));
+        json(self.send(self.authed(req)?).await?).await
+    }
+
+    pub async fn slack_link(&self) -> Result<SlackLinkResponse> {
+        let req = self.client.post(self.url("/account/slack_link"));
+        json(self.send(self.authed(req)?).await?).await
+    }
+
+    pub async fn deliveries(&self) -> Result<Vec<Delivery>> {
+        let req = self.client.get(self.url("/account/deliveries"));
+        let res: DeliveriesResponse = json(self.send(self.authed(req)?).await?).await?;
+        Ok(res.deliveries)
+    }
+
+    pub async fn channel_data(&self, handle: &str) -> Result<ChannelDataResponse> {
+        let req = self
+            .client
+            .get(self.url(&format!("/channel_data/{handle}")));
+        json(self.send(req).await?).await
+    }
+
+    pub async fn search_channels(&self, query: &str) -> Result<Vec<ChannelCandidate>> {
+        let req = self
+            .client
+            .get(self.url("/channel_search"))
+            .query(&[("q", query)]);
+        json(self.send(req).await?).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.host, path)
+    }
+
+    fn authed(&self, req: RequestBuilder) -> Result<RequestBuilder> {
+        let api_key = self.api_key.as_ref().ok_or_eyre("Missing API key")?;
+        Ok(req.header("X-API-KEY", api_key))
+    }
+
+    async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        let res = req.send().await?;
+
+        if !res.status().is_success() {
+            return Err(ApiError {
+                status: res.status().as_u16(),
+                message: res.text().await?,
+            }
+            .into());
+        }
+
+        Ok(res)
+    }
+}
+
+async fn json<T: DeserializeOwned>(res: Response) -> Result<T> {
+    Ok(res.json().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::{Matcher, Server};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_authenticated_requests() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+        let m = server
+            .mock("POST", "/account/resume")
+            .match_header("X-API-KEY", "test-key")
+            .match_body(Matcher::Json(serde_json::json!({ "notifier_index": 1 })))
+            .with_body(r#"{"notifiers": []}"#)
+            .create_async()
+            .await;
+
+        let client = ApiClient::new(Some(&host));
+        assert_eq!(
+            client
+                .resume_notifiers(Some(1))
+                .await
+                .unwrap_err()
+                .to_string(),
+            "Missing API key"
+        );
+
+        let status = client
+            .with_api_key("test-key")
+            .resume_notifiers(Some(1))
+            .await?;
+        assert_eq!(status, AccountStatus::default());
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_settings_request() {
+        let mut settings = UserSettings::default(Default::default());
+        settings.api_key = Some("test-key".to_string());
+        settings.notifiers = vec![];
+
+        let body = serde_json::to_value(SettingsRequest::from(&settings)).unwrap();
+        assert!(body.get("api_key").is_none());
+
+        // Still accepted by the API
+        let parsed: UserSettings = serde_json::from_value(body).unwrap();
+        assert_eq!(parsed.api_key, None);
+    }
+
+    #[tokio::test]
+    async fn test_error_responses() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+        let m = server
+            .mock("GET", "/channel_data/@missing")
+            .with_status(404)
+            .with_body("Channel not found")
+            .create_async()
+            .await;
+
+        let err = ApiClient::new(Some(&host))
+            .channel_data("@missing")
+            .await
+            .unwrap_err();
+        let api_err = err.downcast_ref::<ApiError>().expect("Not an API error");
+
+        assert!(api_err.is_not_found());
+        assert_eq!(api_err.to_string(), "Channel not found");
+
+        m.assert_async().await;
+
+        Ok(())
+    }
+}
@@ -4,12 +4,19 @@ use chrono::{DateTime, Utc};
 use eyre::Result;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 
-use crate::{user_settings::API_HOST, video::Video};
+use crate::{
+    api::{ApiClient, ApiError},
+    video::Video,
+};
 
 const RSS_HOST: &str = "https://www.youtube.com";
 
+const THROTTLED_MESSAGE: &str = "It looks like YouTube API calls are currently throttled.
+
+You can try again later or find the channel data manually:
+https://github.com/pawurb/yt-sub-rs#manually-finding-an-rss-channel_id";
+
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct Channel {
     pub handle: String,
@@ -17,6 +24,42 @@ pub struct Channel {
     pub channel_id: String,
 }
 
+/// Channel search result returned by the `/channel_search` API endpoint.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ChannelCandidate {
+    pub handle: String,
+    pub channel_id: String,
+    pub title: String,
+    // Hidden by some channels
+    pub subscriber_count: Option<u64>,
+    pub thumbnail_url: Option<String>,
+}
+
+impl ChannelCandidate {
+    pub fn to_channel(&self) -> Channel {
+        Channel {
+            handle: self.handle.clone(),
+            description: self.title.clone(),
+            channel_id: self.channel_id.clone(),
+        }
+    }
+}
+
+impl Display for ChannelCandidate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let subscribers = self
+            .subscriber_count
+            .map_or("hidden".to_string(), |count| count.to_string());
+
+        write!(
+            f,
+            "{title} ({handle}), subscribers: {subscribers}",
+            title = self.title,
+            handle = self.handle,
+        )
+    }
+}
+
 impl Display for Channel {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
@@ -62,31 +105,29 @@ impl Channel {
     }
 
     pub async fn get_data(handle: &str, host: Option<&str>) -> Result<(String, String)> {
-        let host = host.unwrap_or(API_HOST);
-        let client = Client::new();
-
-        let res = client
-            .get(format!("{}/channel_data/{}", host, handle))
-            .send()
-            .await?;
-        if res.status() == 404 {
-            eyre::bail!("Channel with handle '{handle}' not found!")
-        }
-
-        if res.status() == 503 {
-            eyre::bail!(
-                "It looks like YouTube API calls are currently throttled.
-
-You can try again later or find the channel data manually:
-https://github.com/pawurb/yt-sub-rs#manually-finding-an-rss-channel_id"
-            );
-        }
-
-        let res_json: Value = res.json().await?;
-        let channel_id = res_json["channel_id"].as_str().unwrap();
-        let channel_name = res_json["channel_name"].as_str().unwrap();
+        let data = ApiClient::new(host)
+            .channel_data(handle)
+            .await
+            .map_err(|e| match e.downcast_ref::<ApiError>() {
+                Some(err) if err.is_not_found() => {
+                    eyre::eyre!("Channel with handle '{handle}' not found!")
+                }
+                Some(err) if err.is_throttled() => eyre::eyre!(THROTTLED_MESSAGE),
+                _ => e,
+            })?;
+
+        Ok((data.channel_id, data.channel_name))
+    }
 
-        Ok((channel_id.to_string(), channel_name.to_string()))
+    pub async fn search(query: &str, host: Option<&str>) -> Result<Vec<ChannelCandidate>> {
+        ApiClient::new(host)
+            .search_channels(query)
+            .await
+            .map_err(|e| match e.downcast_ref::<ApiError>() {
+                Some(err) if err.is_throttled() => eyre::eyre!(THROTTLED_MESSAGE),
+                Some(err) => eyre::eyre!("Channel search failed: {err}"),
+                None => e,
+            })
     }
 
     pub async fn get_fresh_videos(&self, last_run_at: DateTime<Utc>) -> Result<Vec<Video>> {
@@ -96,6 +137,11 @@ https://github.com/pawurb/yt-sub-rs#manually-finding-an-rss-channel_id"
         let videos: Vec<Video> = videos
             .into_iter()
             .filter(|video| video.published_at > last_run_at)
+            .map(|video| Video {
+                channel_handle: self.handle.clone(),
+                channel_id: self.channel_id.clone(),
+                ..video
+            })
             .collect();
 
         Ok(videos)
@@ -110,7 +156,7 @@ https://github.com/pawurb/yt-sub-rs#manually-finding-an-rss-channel_id"
 
 #[cfg(test)]
 mod tests {
-    use mockito::Server;
+    use mockito::{Matcher, Server};
 
     use super::*;
     #[tokio::test]
@@ -168,4 +214,39 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_search_channels() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+        let m = server
+            .mock("GET", "/channel_search")
+            .match_query(Matcher::UrlEncoded("q".into(), "man of recaps".into()))
+            .with_body(
+                r#"[{
+            "handle": "@ManofRecaps",
+            "channel_id": "UCNCTxLZ3EKKry-oWgLlsYsw",
+            "title": "Man of Recaps",
+            "subscriber_count": 2000000,
+            "thumbnail_url": null
+        }]"#,
+            )
+            .create_async()
+            .await;
+
+        let candidates = Channel::search("man of recaps", Some(&host)).await?;
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(
+            candidates[0].to_string(),
+            "Man of Recaps (@ManofRecaps), subscribers: 2000000"
+        );
+        assert_eq!(
+            candidates[0].to_channel().channel_id,
+            "UCNCTxLZ3EKKry-oWgLlsYsw"
+        );
+
+        m.assert_async().await;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,121 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{notifier::Notifier, slack_app::SlackThread, video::Video};
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct PendingVideos {
+    pub videos: Vec<Video>,
+    pub last_delivered_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slack_thread: Option<SlackThread>,
+    // Videos published up to this time were already collected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collected_until: Option<DateTime<Utc>>,
+}
+
+impl PendingVideos {
+    pub fn push(&mut self, videos: &[Video]) {
+        for video in videos {
+            if !self.videos.iter().any(|pending| pending.link == video.link) {
+                self.videos.push(video.clone());
+            }
+        }
+    }
+
+    // Scheduled notifiers collect videos on their own schedule, so the same
+    // videos can be offered more than once
+    pub fn collect(&mut self, videos: &[Video], now: DateTime<Utc>) {
+        let since = self.collected_until;
+        let fresh = videos
+            .iter()
+            .filter(|video| since.is_none_or(|since| video.published_at > since))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        self.push(&fresh);
+        self.collected_until = Some(now);
+    }
+
+    // Without a previous delivery only slots from the last hour are considered
+    pub fn is_due(&self, notifier: &Notifier, now: DateTime<Utc>) -> bool {
+        let Some(schedule) = notifier.schedule() else {
+            return true;
+        };
+
+        let since = self.last_delivered_at.unwrap_or(now - Duration::hours(1));
+        schedule.is_due(since, now)
+    }
+
+    pub fn delivered(&mut self, now: DateTime<Utc>) {
+        self.videos.clear();
+        self.last_delivered_at = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{notifier::SlackConfig, schedule::Schedule};
+
+    use super::*;
+
+    #[test]
+    fn test_pending_videos() {
+        let notifier = Notifier::Slack(SlackConfig {
+            webhook_url: "https://hooks.slack.com/services/XXX".to_string(),
+            channel: "yt-videos".to_string(),
+            schedule: Some(Schedule::Hours(vec![8])),
+            digest: true,
+            blocks: false,
+            templates: Default::default(),
+        });
+
+        let video = Video {
+            channel: "Channel".to_string(),
+            title: "Title".to_string(),
+            link: "https://www.youtube.com/watch?v=1".to_string(),
+            published_at: Utc::now(),
+            thumbnail_url: None,
+            channel_handle: String::new(),
+            channel_id: String::new(),
+        };
+
+        let mut pending = PendingVideos::default();
+        pending.push(std::slice::from_ref(&video));
+        pending.push(&[video]);
+        assert_eq!(pending.videos.len(), 1);
+
+        let morning: DateTime<Utc> = "2024-11-07T08:10:00Z".parse().unwrap();
+        assert!(!pending.is_due(&notifier, morning - Duration::hours(2)));
+        assert!(pending.is_due(&notifier, morning));
+
+        pending.delivered(morning);
+        assert!(pending.videos.is_empty());
+        assert!(!pending.is_due(&notifier, morning + Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_collect_videos() {
+        let video = |id: &str, published_at: DateTime<Utc>| Video {
+            channel: "Channel".to_string(),
+            title: "Title".to_string(),
+            link: format!("https://www.youtube.com/watch?v={id}"),
+            published_at,
+            thumbnail_url: None,
+            channel_handle: String::new(),
+            channel_id: String::new(),
+        };
+        let now: DateTime<Utc> = "2024-11-07T08:10:00Z".parse().unwrap();
+        let first = video("1", now - Duration::minutes(5));
+
+        let mut pending = PendingVideos::default();
+        pending.collect(std::slice::from_ref(&first), now);
+        pending.delivered(now);
+
+        // Already collected videos are not queued again after the delivery
+        let second = video("2", now + Duration::minutes(5));
+        pending.collect(&[first, second], now + Duration::minutes(10));
+        assert_eq!(pending.videos.len(), 1);
+        assert_eq!(pending.videos[0].link, "https://www.youtube.com/watch?v=2");
+    }
+}
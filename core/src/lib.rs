@@ -1,6 +1,14 @@
+pub mod account;
+pub mod api;
 pub mod channel;
+pub mod digest;
 pub mod logger;
+pub mod merge;
 pub mod notifier;
+pub mod notify;
+pub mod schedule;
+pub mod slack_app;
+pub mod templates;
 pub mod user_settings;
 pub mod video;
 pub use user_settings::UserSettings;
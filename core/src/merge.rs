@@ -0,0 +1,251 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde::Serialize;
+
+use crate::{channel::Channel, UserSettings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeSide {
+    Local,
+    Remote,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MergeConflict {
+    pub field: String,
+    pub local: String,
+    pub remote: String,
+}
+
+impl Display for MergeConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: local '{}', remote '{}'",
+            self.field, self.local, self.remote
+        )
+    }
+}
+
+impl UserSettings {
+    /// Three-way merge of local and remote settings. `base` is the remote state from
+    /// the last sync, without it channels are merged as a union and remote values win.
+    /// Conflicting changes are resolved using `prefer` and reported.
+    pub fn merge(
+        &self,
+        remote: &UserSettings,
+        base: Option<&UserSettings>,
+        prefer: MergeSide,
+    ) -> (UserSettings, Vec<MergeConflict>) {
+        let mut conflicts = vec![];
+        let mut channels = vec![];
+
+        for local in &self.channels {
+            let in_base = base.and_then(|b| b.get_channel_by_id(&local.channel_id));
+
+            match remote.get_channel_by_id(&local.channel_id) {
+                Some(remote) => {
+                    channels.push(merge_channel(
+                        local,
+                        &remote,
+                        in_base.as_ref(),
+                        prefer,
+                        &mut conflicts,
+                    ));
+                }
+                // Removed remotely since the last sync
+                None if in_base.is_some() => {}
+                None => channels.push(local.clone()),
+            }
+        }
+
+        for remote in &remote.channels {
+            let added = self.get_channel_by_id(&remote.channel_id).is_none()
+                && base
+                    .and_then(|b| b.get_channel_by_id(&remote.channel_id))
+                    .is_none();
+
+            if added {
+                channels.push(remote.clone());
+            }
+        }
+
+        let notifiers = merge_value(
+            "notifiers",
+            &self.notifiers,
+            &remote.notifiers,
+            base.map(|b| &b.notifiers),
+            prefer,
+            &mut conflicts,
+        );
+        let schedule = merge_value(
+            "schedule",
+            &self.schedule,
+            &remote.schedule,
+            base.map(|b| &b.schedule),
+            prefer,
+            &mut conflicts,
+        );
+        let templates = merge_value(
+            "templates",
+            &self.templates,
+            &remote.templates,
+            base.map(|b| &b.templates),
+            prefer,
+            &mut conflicts,
+        );
+
+        let merged = UserSettings {
+            channels,
+            notifiers,
+            schedule,
+            templates,
+            api_key: self.api_key.clone(),
+            path: self.path.clone(),
+        };
+
+        (merged, conflicts)
+    }
+}
+
+fn merge_channel(
+    local: &Channel,
+    remote: &Channel,
+    base: Option<&Channel>,
+    prefer: MergeSide,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Channel {
+    if local == remote || base == Some(local) {
+        return remote.clone();
+    }
+
+    if base == Some(remote) {
+        return local.clone();
+    }
+
+    conflicts.push(MergeConflict {
+        field: format!("channel {}", local.channel_id),
+        local: local.handle.clone(),
+        remote: remote.handle.clone(),
+    });
+
+    match prefer {
+        MergeSide::Local => local.clone(),
+        MergeSide::Remote => remote.clone(),
+    }
+}
+
+fn merge_value<T: Clone + PartialEq + Serialize>(
+    field: &str,
+    local: &T,
+    remote: &T,
+    base: Option<&T>,
+    prefer: MergeSide,
+    conflicts: &mut Vec<MergeConflict>,
+) -> T {
+    let Some(base) = base else {
+        return remote.clone();
+    };
+
+    if local == remote || local == base {
+        return remote.clone();
+    }
+
+    if remote == base {
+        return local.clone();
+    }
+
+    let to_json = |value: &T| serde_json::to_string(value).unwrap_or_default();
+    conflicts.push(MergeConflict {
+        field: field.to_string(),
+        local: to_json(local),
+        remote: to_json(remote),
+    });
+
+    match prefer {
+        MergeSide::Local => local.clone(),
+        MergeSide::Remote => remote.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::schedule::Schedule;
+
+    use super::*;
+
+    fn channel(id: &str, handle: &str) -> Channel {
+        Channel {
+            handle: handle.to_string(),
+            description: id.to_string(),
+            channel_id: id.to_string(),
+        }
+    }
+
+    fn settings(channels: Vec<Channel>) -> UserSettings {
+        UserSettings {
+            channels,
+            ..UserSettings::default(PathBuf::from("test.toml"))
+        }
+    }
+
+    #[test]
+    fn test_merge_without_base() {
+        let local = settings(vec![channel("A", "@a"), channel("B", "@b")]);
+        let mut remote = settings(vec![channel("B", "@b"), channel("C", "@c")]);
+        remote.schedule = Some(Schedule::Hours(vec![8]));
+
+        let (merged, conflicts) = local.merge(&remote, None, MergeSide::Local);
+
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            merged.channels,
+            vec![channel("A", "@a"), channel("B", "@b"), channel("C", "@c")]
+        );
+        assert_eq!(merged.schedule, Some(Schedule::Hours(vec![8])));
+    }
+
+    #[test]
+    fn test_merge_with_base() {
+        let base = settings(vec![channel("A", "@a"), channel("B", "@b")]);
+        // B removed locally, C added locally
+        let local = settings(vec![channel("A", "@a"), channel("C", "@c")]);
+        // A removed remotely, D added remotely
+        let mut remote = settings(vec![channel("B", "@b"), channel("D", "@d")]);
+        remote.schedule = Some(Schedule::Hours(vec![8]));
+
+        let (merged, conflicts) = local.merge(&remote, Some(&base), MergeSide::Local);
+
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            merged.channels,
+            vec![channel("C", "@c"), channel("D", "@d")]
+        );
+        assert_eq!(merged.schedule, Some(Schedule::Hours(vec![8])));
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let base = settings(vec![]);
+        let mut local = settings(vec![channel("A", "@local")]);
+        local.schedule = Some(Schedule::Hours(vec![8]));
+        let mut remote = settings(vec![channel("A", "@remote")]);
+        remote.schedule = Some(Schedule::Hours(vec![20]));
+
+        let (merged, conflicts) = local.merge(&remote, Some(&base), MergeSide::Remote);
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "channel A: local '@local', remote '@remote'"
+        );
+        assert_eq!(merged.channels, vec![channel("A", "@remote")]);
+        assert_eq!(merged.schedule, Some(Schedule::Hours(vec![20])));
+
+        let (merged, _) = local.merge(&remote, Some(&base), MergeSide::Local);
+        assert_eq!(merged.channels, vec![channel("A", "@local")]);
+        assert_eq!(merged.schedule, Some(Schedule::Hours(vec![8])));
+    }
+}
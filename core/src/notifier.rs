@@ -1,16 +1,28 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use eyre::Result;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{json, Value};
 
-use crate::logger::Logger;
+use crate::{
+    digest::PendingVideos,
+    logger::Logger,
+    notify::{build_custom, CustomConfig, Notify},
+    schedule::Schedule,
+    slack_app::SlackAppConfig,
+    templates::Templates,
+    video::Video,
+};
 
 #[non_exhaustive]
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub enum Notifier {
     Log(),
     Slack(SlackConfig),
+    SlackApp(SlackAppConfig),
     Telegram,
+    Custom(CustomConfig),
 }
 
 impl Default for Notifier {
@@ -23,45 +35,461 @@ impl Default for Notifier {
 pub struct SlackConfig {
     pub webhook_url: String,
     pub channel: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub digest: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub blocks: bool,
+    #[serde(default, skip_serializing_if = "Templates::is_empty")]
+    pub templates: Templates,
+}
+
+// Slack rejects messages with more than 50 blocks
+const SLACK_BLOCKS_LIMIT: usize = 50;
+// Header and footer blocks
+const EXTRA_BLOCKS: usize = 2;
+const BLOCKS_PER_VIDEO: usize = 3;
+
+pub(crate) fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[derive(Debug)]
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notify for LogNotifier {
+    fn name(&self) -> &str {
+        "Log"
+    }
+
+    async fn send(&self, messages: Vec<String>, cron: bool) -> Result<()> {
+        let logger = Logger::new(cron);
+        for message in messages {
+            logger.info(&message);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notify for SlackConfig {
+    fn name(&self) -> &str {
+        "Slack"
+    }
+
+    fn validate(&self) -> Result<()> {
+        reqwest::Url::parse(&self.webhook_url)
+            .map_err(|e| eyre::eyre!("Invalid Slack webhook_url '{}': {e}", self.webhook_url))?;
+        if self.channel.is_empty() {
+            eyre::bail!("Slack channel must not be empty")
+        }
+        if self.digest && self.blocks {
+            eyre::bail!("Slack digest and blocks can't be enabled together")
+        }
+        Ok(())
+    }
+
+    fn render(&self, video: &Video, templates: &Templates) -> String {
+        templates
+            .render_video(video)
+            .unwrap_or_else(|| video.mrkdwn_text())
+    }
+
+    async fn send(&self, messages: Vec<String>, _cron: bool) -> Result<()> {
+        let payload = slack_payload(&messages.join("\n\n"), self);
+        post_slack(&payload, self).await
+    }
 }
 
 impl Notifier {
+    // Built-in configs are cloned, custom ones are built by the registered factory
+    pub fn backend(&self) -> Result<Box<dyn Notify>> {
+        match self {
+            Notifier::Log() => Ok(Box::new(LogNotifier)),
+            Notifier::Slack(slack_config) => Ok(Box::new(slack_config.clone())),
+            Notifier::SlackApp(app_config) => Ok(Box::new(app_config.clone())),
+            Notifier::Telegram => eyre::bail!("Telegram notifier is not supported yet"),
+            Notifier::Custom(custom_config) => build_custom(custom_config),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.backend()?.validate()
+    }
+
     pub async fn notify(&self, messages: Vec<String>, cron: bool) -> Result<()> {
+        self.backend()?.send(messages, cron).await
+    }
+
+    // Checks the config and confirms it works by sending a message
+    pub async fn verify(&self, message: &str) -> Result<()> {
+        let backend = self.backend()?;
+        backend.validate()?;
+        backend.send(vec![message.to_string()], false).await
+    }
+
+    // Stateful notifiers read and update the persisted `state`
+    pub async fn deliver(
+        &self,
+        videos: &[Video],
+        state: &mut PendingVideos,
+        templates: &Templates,
+        cron: bool,
+    ) -> Result<()> {
+        let mut sent = 0;
+        self.deliver_from(videos, state, templates, cron, &mut sent)
+            .await
+    }
+
+    // Slack messages can be split into several posts. The first `sent` posts are
+    // skipped, and `sent` is advanced after each one, so a retry doesn't repeat them.
+    pub async fn deliver_from(
+        &self,
+        videos: &[Video],
+        state: &mut PendingVideos,
+        templates: &Templates,
+        cron: bool,
+        sent: &mut usize,
+    ) -> Result<()> {
         match self {
-            Notifier::Log() => {
-                let logger = Logger::new(cron);
-                for message in messages {
-                    logger.info(&message);
+            Notifier::Slack(slack_config) => {
+                let payloads = self.slack_payloads(videos, slack_config, templates);
+                for (index, payload) in payloads.iter().enumerate().skip(*sent) {
+                    post_slack(payload, slack_config).await?;
+                    *sent = index + 1;
                 }
                 Ok(())
             }
-            Notifier::Slack(slack_config) => {
-                notify_slack(&messages.join("\n\n"), slack_config).await?;
+            Notifier::SlackApp(app_config) => {
+                let thread = app_config
+                    .thread(state.slack_thread.as_ref(), Utc::now(), None)
+                    .await?;
+                state.slack_thread = Some(thread.clone());
+
+                let payloads = self.slack_app_payloads(videos, app_config, &thread.ts, templates);
+                for (index, payload) in payloads.iter().enumerate().skip(*sent) {
+                    app_config.post_message(payload, None).await?;
+                    *sent = index + 1;
+                }
                 Ok(())
             }
-            Notifier::Telegram => todo!(),
+            _ => {
+                if *sent == 0 {
+                    self.notify(self.notifications(videos, templates), cron)
+                        .await?;
+                    *sent = 1;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn preview(&self, videos: &[Video], templates: &Templates) -> String {
+        match self {
+            Notifier::Slack(slack_config) => self
+                .slack_payloads(videos, slack_config, templates)
+                .iter()
+                .map(|payload| {
+                    serde_json::to_string_pretty(payload).expect("Failed to serialize JSON")
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Notifier::SlackApp(app_config) => {
+                let parent = app_config
+                    .message_payload(&SlackAppConfig::parent_text(Utc::now().date_naive()), None);
+
+                std::iter::once(parent)
+                    .chain(self.slack_app_payloads(videos, app_config, "<parent_ts>", templates))
+                    .map(|payload| {
+                        serde_json::to_string_pretty(&payload).expect("Failed to serialize JSON")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            _ => self.notifications(videos, templates).join("\n"),
+        }
+    }
+
+    // Each video is posted as a separate reply in the thread
+    fn slack_app_payloads(
+        &self,
+        videos: &[Video],
+        config: &SlackAppConfig,
+        thread_ts: &str,
+        templates: &Templates,
+    ) -> Vec<Value> {
+        let header = templates.render_header(videos);
+        let footer = templates.render_footer(videos);
+
+        let replies = videos.iter().map(|video| {
+            let mut payload =
+                config.message_payload(&video.notification_text(self, templates), Some(thread_ts));
+            if config.blocks {
+                payload["blocks"] = json!(video_blocks(video));
+            }
+            payload
+        });
+
+        header
+            .map(|text| config.message_payload(&text, Some(thread_ts)))
+            .into_iter()
+            .chain(replies)
+            .chain(footer.map(|text| config.message_payload(&text, Some(thread_ts))))
+            .collect()
+    }
+
+    fn slack_payloads(
+        &self,
+        videos: &[Video],
+        config: &SlackConfig,
+        templates: &Templates,
+    ) -> Vec<Value> {
+        if !config.blocks {
+            let message = self.notifications(videos, templates).join("\n\n");
+            return vec![slack_payload(&message, config)];
+        }
+
+        let header_text = templates
+            .render_header(videos)
+            .unwrap_or_else(|| format!("{} new videos", videos.len()));
+        let footer = templates.render_footer(videos);
+
+        let per_message = (SLACK_BLOCKS_LIMIT - EXTRA_BLOCKS) / BLOCKS_PER_VIDEO;
+        let chunks = videos.chunks(per_message).collect::<Vec<_>>();
+
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let header = if chunks.len() > 1 {
+                    format!("{header_text} ({}/{})", index + 1, chunks.len())
+                } else {
+                    header_text.clone()
+                };
+
+                let mut blocks = vec![json!({
+                    "type": "header",
+                    "text": { "type": "plain_text", "text": header },
+                })];
+                blocks.extend(chunk.iter().flat_map(video_blocks));
+
+                if let Some(footer) = footer.as_ref().filter(|_| index == chunks.len() - 1) {
+                    blocks.push(json!({
+                        "type": "context",
+                        "elements": [{ "type": "mrkdwn", "text": footer }],
+                    }));
+                }
+
+                // Plain text is displayed in notifications and by clients without Block Kit support
+                let fallback = chunk
+                    .iter()
+                    .map(|video| video.notification_text(self, templates))
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+
+                let mut payload = slack_payload(&fallback, config);
+                payload["blocks"] = json!(blocks);
+                payload
+            })
+            .collect()
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Notifier::Log() => "Log",
+            Notifier::Slack(_) => "Slack",
+            Notifier::SlackApp(_) => "SlackApp",
+            Notifier::Telegram => "Telegram",
+            Notifier::Custom(custom_config) => &custom_config.kind,
+        }
+    }
+
+    pub fn schedule(&self) -> Option<&Schedule> {
+        match self {
+            Notifier::Slack(slack_config) => slack_config.schedule.as_ref(),
+            Notifier::SlackApp(app_config) => app_config.schedule.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn templates(&self) -> Option<&Templates> {
+        match self {
+            Notifier::Slack(slack_config) => Some(&slack_config.templates),
+            Notifier::SlackApp(app_config) => Some(&app_config.templates),
+            _ => None,
+        }
+    }
+
+    pub fn is_digest(&self) -> bool {
+        match self {
+            Notifier::Slack(slack_config) => slack_config.digest,
+            _ => false,
+        }
+    }
+
+    // Scheduled and digest notifiers accumulate videos between deliveries
+    pub fn is_batched(&self) -> bool {
+        self.schedule().is_some() || self.is_digest()
+    }
+
+    pub fn slack_channel(&self) -> Option<&str> {
+        match self {
+            Notifier::Slack(slack_config) => Some(&slack_config.channel),
+            Notifier::SlackApp(app_config) => Some(&app_config.channel),
+            _ => None,
+        }
+    }
+
+    pub fn is_stateful(&self) -> bool {
+        self.is_batched() || matches!(self, Notifier::SlackApp(_))
+    }
+
+    pub fn uses_mrkdwn(&self) -> bool {
+        matches!(self, Notifier::Slack(_) | Notifier::SlackApp(_))
+    }
+
+    pub fn notifications(&self, videos: &[Video], templates: &Templates) -> Vec<String> {
+        let messages = if self.is_digest() {
+            vec![self.digest_text(videos)]
+        } else {
+            videos
+                .iter()
+                .map(|video| video.notification_text(self, templates))
+                .collect()
+        };
+
+        templates
+            .render_header(videos)
+            .into_iter()
+            .chain(messages)
+            .chain(templates.render_footer(videos))
+            .collect()
+    }
+
+    pub fn digest_text(&self, videos: &[Video]) -> String {
+        let mut channels: Vec<&str> = vec![];
+        for video in videos {
+            if !channels.contains(&video.channel.as_str()) {
+                channels.push(&video.channel);
+            }
         }
+
+        let groups = channels
+            .iter()
+            .map(|channel| {
+                let titles = videos
+                    .iter()
+                    .filter(|video| video.channel == *channel)
+                    .map(|video| {
+                        if self.uses_mrkdwn() {
+                            format!("• <{}|{}>", video.link, video.title)
+                        } else {
+                            format!("- {} {}", video.title, video.link)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                if self.uses_mrkdwn() {
+                    format!("*{channel}*\n{titles}")
+                } else {
+                    format!("{channel}\n{titles}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let header = if self.uses_mrkdwn() {
+            format!("*New videos digest - {}*", videos.len())
+        } else {
+            format!("New videos digest - {}", videos.len())
+        };
+
+        format!("{header}\n\n{groups}")
+    }
+
+    pub fn is_log(&self) -> bool {
+        matches!(self, Notifier::Log())
     }
 
     pub fn is_slack(&self) -> bool {
-        matches!(self, Notifier::Slack(_))
+        matches!(self, Notifier::Slack(_) | Notifier::SlackApp(_))
     }
 }
 
-async fn notify_slack(message: &str, config: &SlackConfig) -> Result<()> {
-    let client = Client::new();
-
-    let payload = json!({
+fn slack_payload(message: &str, config: &SlackConfig) -> Value {
+    json!({
         "channel": config.channel,
         "icon_emoji": ":exclamation:",
         "username": "yt-sub-rs",
         "text": message,
         "unfurl_links": false,
+    })
+}
+
+fn video_blocks(video: &Video) -> Vec<Value> {
+    let mut section = json!({
+        "type": "section",
+        "text": {
+            "type": "mrkdwn",
+            "text": format!("*<{}|{}>*", video.link, escape_mrkdwn(&video.title)),
+        },
     });
 
+    if let Some(thumbnail_url) = &video.thumbnail_url {
+        section["accessory"] = json!({
+            "type": "image",
+            "image_url": thumbnail_url,
+            "alt_text": video.title,
+        });
+    }
+
+    vec![
+        section,
+        json!({
+            "type": "context",
+            "elements": [{
+                "type": "mrkdwn",
+                "text": format!(
+                    "{} • {}",
+                    escape_mrkdwn(&video.channel),
+                    slack_date(video.published_at)
+                ),
+            }],
+        }),
+        json!({
+            "type": "actions",
+            "elements": [{
+                "type": "button",
+                "text": { "type": "plain_text", "text": "Watch" },
+                "url": video.link,
+            }],
+        }),
+    ]
+}
+
+fn slack_date(date: DateTime<Utc>) -> String {
+    format!(
+        "<!date^{}^{{date_short_pretty}} at {{time}}|{}>",
+        date.timestamp(),
+        date.format("%Y-%m-%d %H:%M UTC")
+    )
+}
+
+fn escape_mrkdwn(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+async fn post_slack(payload: &Value, config: &SlackConfig) -> Result<()> {
+    let client = Client::new();
+
     let res = client
         .post(&config.webhook_url)
-        .json(&payload)
+        .json(payload)
         .send()
         .await?;
 
@@ -72,3 +500,215 @@ async fn notify_slack(message: &str, config: &SlackConfig) -> Result<()> {
     let err_msg = res.text().await?;
     eyre::bail!("Failed to send message to Slack: {err_msg}");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(channel: &str, title: &str) -> Video {
+        Video {
+            channel: channel.to_string(),
+            title: title.to_string(),
+            link: format!("https://www.youtube.com/watch?v={title}"),
+            published_at: Utc::now(),
+            thumbnail_url: None,
+            channel_handle: String::new(),
+            channel_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_slack_preview() {
+        let notifier = Notifier::Slack(SlackConfig {
+            webhook_url: "https://hooks.slack.com/services/XXX".to_string(),
+            channel: "yt-videos".to_string(),
+            schedule: None,
+            digest: false,
+            blocks: false,
+            templates: Templates::default(),
+        });
+
+        let preview = notifier.preview(&[video("A", "1"), video("B", "2")], &Templates::default());
+        let payload: Value = serde_json::from_str(&preview).unwrap();
+
+        assert_eq!(payload["channel"], "yt-videos");
+        assert_eq!(
+            payload["text"],
+            "*New video - A* <https://www.youtube.com/watch?v=1|1>\n\n*New video - B* <https://www.youtube.com/watch?v=2|2>"
+        );
+        assert_eq!(payload["unfurl_links"], false);
+    }
+
+    #[test]
+    fn test_slack_blocks_payloads() {
+        let config = SlackConfig {
+            webhook_url: "https://hooks.slack.com/services/XXX".to_string(),
+            channel: "yt-videos".to_string(),
+            schedule: None,
+            digest: false,
+            blocks: true,
+            templates: Templates::default(),
+        };
+        let notifier = Notifier::Slack(config.clone());
+
+        let mut first = video("A", "1");
+        first.thumbnail_url = Some("https://i4.ytimg.com/vi/1/hqdefault.jpg".to_string());
+
+        let payloads = notifier.slack_payloads(&[first], &config, &Templates::default());
+        assert_eq!(payloads.len(), 1);
+
+        let blocks = payloads[0]["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), 1 + BLOCKS_PER_VIDEO);
+        assert_eq!(blocks[0]["text"]["text"], "1 new videos");
+        assert_eq!(
+            blocks[1]["accessory"]["image_url"],
+            "https://i4.ytimg.com/vi/1/hqdefault.jpg"
+        );
+        assert_eq!(
+            blocks[3]["elements"][0]["url"],
+            "https://www.youtube.com/watch?v=1"
+        );
+        assert_eq!(
+            payloads[0]["text"],
+            "*New video - A* <https://www.youtube.com/watch?v=1|1>"
+        );
+
+        let videos = (0..40)
+            .map(|i| video("A", &i.to_string()))
+            .collect::<Vec<_>>();
+        let payloads = notifier.slack_payloads(&videos, &config, &Templates::default());
+        assert_eq!(payloads.len(), 3);
+
+        for payload in payloads {
+            assert!(payload["blocks"].as_array().unwrap().len() <= SLACK_BLOCKS_LIMIT);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_partial_delivery() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let config = SlackConfig {
+            webhook_url: format!("http://{}/webhook", server.host_with_port()),
+            channel: "yt-videos".to_string(),
+            schedule: None,
+            digest: false,
+            blocks: true,
+            templates: Templates::default(),
+        };
+        let notifier = Notifier::Slack(config);
+        let videos = (0..40)
+            .map(|i| video("A", &i.to_string()))
+            .collect::<Vec<_>>();
+
+        let first = server
+            .mock("POST", "/webhook")
+            .match_body(mockito::Matcher::Regex(r"\(1/3\)".to_string()))
+            .create_async()
+            .await;
+        let failing = server
+            .mock("POST", "/webhook")
+            .match_body(mockito::Matcher::Regex(r"\(2/3\)".to_string()))
+            .with_status(500)
+            .create_async()
+            .await;
+
+        let mut sent = 0;
+        let mut state = PendingVideos::default();
+        let res = notifier
+            .deliver_from(&videos, &mut state, &Templates::default(), false, &mut sent)
+            .await;
+        assert!(res.is_err());
+        assert_eq!(sent, 1);
+        first.assert_async().await;
+        failing.remove_async().await;
+
+        // The retry starts from the failed message
+        let rest = server
+            .mock("POST", "/webhook")
+            .match_body(mockito::Matcher::Regex(r"\((2|3)/3\)".to_string()))
+            .expect(2)
+            .create_async()
+            .await;
+
+        notifier
+            .deliver_from(&videos, &mut state, &Templates::default(), false, &mut sent)
+            .await?;
+        assert_eq!(sent, 3);
+        rest.assert_async().await;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_digest_notifications() {
+        let notifier = Notifier::Slack(SlackConfig {
+            webhook_url: "https://hooks.slack.com/services/XXX".to_string(),
+            channel: "yt-videos".to_string(),
+            schedule: Some(Schedule::Hours(vec![8])),
+            digest: true,
+            blocks: false,
+            templates: Templates::default(),
+        });
+        assert!(notifier.is_batched());
+
+        let videos = vec![video("A", "1"), video("B", "2"), video("A", "3")];
+        let notifications = notifier.notifications(&videos, &Templates::default());
+
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(
+            notifications[0],
+            "*New videos digest - 3*
+
+*A*
+• <https://www.youtube.com/watch?v=1|1>
+• <https://www.youtube.com/watch?v=3|3>
+
+*B*
+• <https://www.youtube.com/watch?v=2|2>"
+        );
+    }
+
+    #[test]
+    fn test_templated_notifications() {
+        let notifier = Notifier::Log();
+        let templates = Templates {
+            video: Some("{{ video.title }} by {{ channel.name }}".to_string()),
+            header: Some("{{ count }} new videos".to_string()),
+            footer: Some("--".to_string()),
+        };
+
+        let notifications = notifier.notifications(&[video("A", "1"), video("B", "2")], &templates);
+        assert_eq!(
+            notifications,
+            vec!["2 new videos", "1 by A", "2 by B", "--"]
+        );
+    }
+
+    #[test]
+    fn test_validate_notifiers() {
+        let mut config = SlackConfig {
+            webhook_url: "https://hooks.slack.com/services/XXX".to_string(),
+            channel: "yt-videos".to_string(),
+            schedule: None,
+            digest: false,
+            blocks: false,
+            templates: Templates::default(),
+        };
+        assert!(Notifier::Slack(config.clone()).validate().is_ok());
+
+        let err = Notifier::Slack(SlackConfig {
+            digest: true,
+            blocks: true,
+            ..config.clone()
+        })
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("digest and blocks"));
+
+        config.webhook_url = "hooks.slack.com".to_string();
+        let err = Notifier::Slack(config).validate().unwrap_err();
+        assert!(err.to_string().contains("Invalid Slack webhook_url"));
+
+        assert!(Notifier::Telegram.validate().is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{LazyLock, RwLock},
+};
+
+use async_trait::async_trait;
+use eyre::Result;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{templates::Templates, video::Video};
+
+/// Delivery backend implemented by the built-in notifiers. Library users can
+/// implement it for their own targets and make them available with [`register_notifier`].
+#[async_trait]
+pub trait Notify: Debug + Send + Sync {
+    fn name(&self) -> &str;
+
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn render(&self, video: &Video, templates: &Templates) -> String {
+        templates
+            .render_video(video)
+            .unwrap_or_else(|| video.plain_text())
+    }
+
+    async fn send(&self, messages: Vec<String>, cron: bool) -> Result<()>;
+}
+
+/// Config of a notifier registered by a downstream crate:
+///
+/// ```toml
+/// [[notifiers]]
+///
+/// [notifiers.Custom]
+/// type = "Discord"
+/// webhook_url = "https://discord.com/api/webhooks/XXX"
+/// ```
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct CustomConfig {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(flatten)]
+    pub settings: Map<String, Value>,
+}
+
+impl CustomConfig {
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_value(Value::Object(self.settings.clone()))
+            .map_err(|e| eyre::eyre!("Invalid '{}' notifier config: {e}", self.kind))
+    }
+}
+
+pub type NotifierFactory = Box<dyn Fn(&CustomConfig) -> Result<Box<dyn Notify>> + Send + Sync>;
+
+static REGISTRY: LazyLock<RwLock<HashMap<String, NotifierFactory>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Registers a factory building notifiers of a given `type`, replacing any previous one
+pub fn register_notifier<F>(kind: &str, factory: F)
+where
+    F: Fn(&CustomConfig) -> Result<Box<dyn Notify>> + Send + Sync + 'static,
+{
+    REGISTRY
+        .write()
+        .expect("Notifier registry lock poisoned")
+        .insert(kind.to_string(), Box::new(factory));
+}
+
+pub fn build_custom(config: &CustomConfig) -> Result<Box<dyn Notify>> {
+    let registry = REGISTRY.read().expect("Notifier registry lock poisoned");
+
+    match registry.get(&config.kind) {
+        Some(factory) => factory(config),
+        None => eyre::bail!("Unknown notifier type '{}'", config.kind),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::notifier::Notifier;
+
+    static SENT: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+    #[derive(Debug, Deserialize)]
+    struct MemoryNotifier {
+        prefix: String,
+    }
+
+    #[async_trait]
+    impl Notify for MemoryNotifier {
+        fn name(&self) -> &str {
+            "Memory"
+        }
+
+        fn validate(&self) -> Result<()> {
+            if self.prefix.is_empty() {
+                eyre::bail!("Missing prefix")
+            }
+            Ok(())
+        }
+
+        fn render(&self, video: &Video, _templates: &Templates) -> String {
+            format!("{} {}", self.prefix, video.title)
+        }
+
+        async fn send(&self, messages: Vec<String>, _cron: bool) -> Result<()> {
+            SENT.lock().unwrap().extend(messages);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_notifier() -> Result<()> {
+        register_notifier("Memory", |config| {
+            Ok(Box::new(config.parse::<MemoryNotifier>()?) as Box<dyn Notify>)
+        });
+
+        let notifier: Notifier = toml::from_str(
+            r#"
+[Custom]
+type = "Memory"
+prefix = ">>"
+"#,
+        )?;
+        notifier.validate()?;
+        assert_eq!(notifier.name(), "Memory");
+
+        let video = Video {
+            channel: "Channel".to_string(),
+            title: "Title".to_string(),
+            link: "https://www.youtube.com/watch?v=1".to_string(),
+            published_at: Utc::now(),
+            thumbnail_url: None,
+            channel_handle: String::new(),
+            channel_id: String::new(),
+        };
+
+        let messages = notifier.notifications(&[video], &Templates::default());
+        notifier.notify(messages, false).await?;
+        assert_eq!(*SENT.lock().unwrap(), vec![">> Title"]);
+
+        let unknown: Notifier = toml::from_str("[Custom]\ntype = \"Unknown\"")?;
+        assert!(unknown.validate().is_err());
+
+        Ok(())
+    }
+}
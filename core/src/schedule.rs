@@ -0,0 +1,300 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+// Scheduled slots are searched up to this many days ahead
+const LOOKAHEAD_DAYS: i64 = 8;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum Schedule {
+    /// UTC hours of the day, e.g. `[8, 20]`
+    Hours(Vec<u32>),
+    /// Cron expression in UTC, e.g. `"0 9 * * Mon-Fri"`
+    Cron(String),
+    /// Days and times in a given timezone
+    Blocks(Vec<ScheduleBlock>),
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ScheduleBlock {
+    #[serde(default)]
+    pub days: Vec<String>,
+    pub times: Vec<String>,
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Hours(hours) => {
+                let hours = hours
+                    .iter()
+                    .map(|h| format!("{h:02}:00"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{hours} UTC")
+            }
+            Schedule::Cron(expr) => write!(f, "cron '{expr}' UTC"),
+            Schedule::Blocks(blocks) => {
+                let blocks = blocks
+                    .iter()
+                    .map(|block| {
+                        let days = if block.days.is_empty() {
+                            "every day".to_string()
+                        } else {
+                            block.days.join(", ")
+                        };
+                        format!("{days} at {} {}", block.times.join(", "), block.timezone)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                write!(f, "{blocks}")
+            }
+        }
+    }
+}
+
+impl Schedule {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Schedule::Hours(hours) => {
+                if let Some(hour) = hours.iter().find(|&&h| h > 23) {
+                    eyre::bail!("Invalid schedule hour '{hour}', expected a value between 0 and 23")
+                }
+            }
+            Schedule::Cron(expr) => {
+                parse_cron(expr)?;
+            }
+            Schedule::Blocks(blocks) => {
+                for block in blocks {
+                    block.parse()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// First scheduled slot strictly after `since`
+    pub fn next_after(&self, since: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Hours(hours) => {
+                let block = ParsedBlock {
+                    days: vec![],
+                    times: hours
+                        .iter()
+                        .filter_map(|&h| NaiveTime::from_hms_opt(h, 0, 0))
+                        .collect(),
+                    timezone: Tz::UTC,
+                };
+                block.next_after(since)
+            }
+            Schedule::Cron(expr) => parse_cron(expr).ok()?.after(&since).next(),
+            Schedule::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| block.parse().ok()?.next_after(since))
+                .min(),
+        }
+    }
+
+    /// Whether any slot falls within `(since, now]`, so missed slots are caught up
+    pub fn is_due(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.next_after(since).is_some_and(|slot| slot <= now)
+    }
+}
+
+struct ParsedBlock {
+    days: Vec<Weekday>,
+    times: Vec<NaiveTime>,
+    timezone: Tz,
+}
+
+impl ScheduleBlock {
+    fn parse(&self) -> Result<ParsedBlock> {
+        let timezone = Tz::from_str(&self.timezone)
+            .map_err(|_| eyre::eyre!("Invalid schedule timezone '{}'", self.timezone))?;
+
+        if self.times.is_empty() {
+            eyre::bail!("Schedule block must define at least one time")
+        }
+
+        let times = self
+            .times
+            .iter()
+            .map(|time| {
+                NaiveTime::parse_from_str(time, "%H:%M")
+                    .map_err(|_| eyre::eyre!("Invalid schedule time '{time}', expected HH:MM"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut days = vec![];
+        for day in &self.days {
+            match day.to_lowercase().as_str() {
+                "weekdays" => days.extend([
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                ]),
+                "weekends" => days.extend([Weekday::Sat, Weekday::Sun]),
+                _ => days.push(
+                    Weekday::from_str(day)
+                        .map_err(|_| eyre::eyre!("Invalid schedule day '{day}'"))?,
+                ),
+            }
+        }
+
+        Ok(ParsedBlock {
+            days,
+            times,
+            timezone,
+        })
+    }
+}
+
+impl ParsedBlock {
+    fn next_after(&self, since: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = since.with_timezone(&self.timezone).date_naive();
+
+        (0..LOOKAHEAD_DAYS)
+            .map(|offset| start + Duration::days(offset))
+            .filter(|date| self.days.is_empty() || self.days.contains(&date.weekday()))
+            .flat_map(|date| {
+                self.times.iter().filter_map(move |time| {
+                    self.timezone
+                        .from_local_datetime(&date.and_time(*time))
+                        .earliest()
+                })
+            })
+            .map(|slot| slot.with_timezone(&Utc))
+            .filter(|slot| *slot > since)
+            .min()
+    }
+}
+
+// The cron crate expects a leading seconds field, standard 5 field expressions are supported too
+fn parse_cron(expr: &str) -> Result<cron::Schedule> {
+    let expr = if expr.split_whitespace().count() == 5 {
+        format!("0 {expr}")
+    } else {
+        expr.to_string()
+    };
+
+    cron::Schedule::from_str(&expr)
+        .map_err(|e| eyre::eyre!("Invalid schedule cron expression '{expr}': {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_hours_schedule() {
+        let schedule = Schedule::Hours(vec![8, 20]);
+
+        assert_eq!(
+            schedule.next_after(time("2024-11-07T08:30:00Z")),
+            Some(time("2024-11-07T20:00:00Z"))
+        );
+        assert!(schedule.is_due(time("2024-11-07T07:00:00Z"), time("2024-11-07T08:05:00Z")));
+        assert!(!schedule.is_due(time("2024-11-07T08:01:00Z"), time("2024-11-07T09:00:00Z")));
+    }
+
+    #[test]
+    fn test_catch_up_missed_slot() {
+        let schedule = Schedule::Hours(vec![8]);
+
+        // 08:00 check was skipped, the next one still notifies
+        assert!(schedule.is_due(time("2024-11-07T07:00:00Z"), time("2024-11-07T10:00:00Z")));
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        let schedule = Schedule::Cron("0 9 * * Mon-Fri".to_string());
+        schedule.validate().unwrap();
+
+        // Friday evening, next slot is on Monday
+        assert_eq!(
+            schedule.next_after(time("2024-11-08T10:00:00Z")),
+            Some(time("2024-11-11T09:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_timezone_block_schedule() {
+        let schedule = Schedule::Blocks(vec![ScheduleBlock {
+            days: vec!["weekdays".to_string()],
+            times: vec!["09:00".to_string()],
+            timezone: "Europe/Warsaw".to_string(),
+        }]);
+        schedule.validate().unwrap();
+
+        // CET is UTC+1 in November
+        assert_eq!(
+            schedule.next_after(time("2024-11-07T09:00:00Z")),
+            Some(time("2024-11-08T08:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        assert!(Schedule::Hours(vec![24]).validate().is_err());
+        assert!(Schedule::Cron("not a cron".to_string()).validate().is_err());
+
+        let block = ScheduleBlock {
+            days: vec![],
+            times: vec!["9am".to_string()],
+            timezone: "UTC".to_string(),
+        };
+        assert!(Schedule::Blocks(vec![block]).validate().is_err());
+
+        let block = ScheduleBlock {
+            days: vec!["Someday".to_string()],
+            times: vec!["09:00".to_string()],
+            timezone: "Mars/Olympus".to_string(),
+        };
+        assert!(Schedule::Blocks(vec![block]).validate().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_schedule() {
+        #[derive(Deserialize)]
+        struct Config {
+            schedule: Schedule,
+        }
+
+        let config: Config = toml::from_str("schedule = [8, 20]").unwrap();
+        assert_eq!(config.schedule, Schedule::Hours(vec![8, 20]));
+
+        let config: Config = toml::from_str(r#"schedule = "0 9 * * *""#).unwrap();
+        assert_eq!(config.schedule, Schedule::Cron("0 9 * * *".to_string()));
+
+        let config: Config = toml::from_str(
+            r#"
+[[schedule]]
+days = ["Mon", "Tue"]
+times = ["09:00"]
+timezone = "Europe/Warsaw"
+"#,
+        )
+        .unwrap();
+        assert!(matches!(config.schedule, Schedule::Blocks(_)));
+    }
+}
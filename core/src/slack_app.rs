@@ -0,0 +1,223 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use eyre::Result;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    notifier::is_false, notify::Notify, schedule::Schedule, templates::Templates, video::Video,
+};
+
+const SLACK_API_HOST: &str = "https://slack.com";
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct SlackAppConfig {
+    pub bot_token: String,
+    pub channel: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub blocks: bool,
+    #[serde(default, skip_serializing_if = "Templates::is_empty")]
+    pub templates: Templates,
+}
+
+// Parent message that videos published on a given day are threaded under
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct SlackThread {
+    pub date: NaiveDate,
+    pub ts: String,
+}
+
+impl SlackAppConfig {
+    pub fn message_payload(&self, text: &str, thread_ts: Option<&str>) -> Value {
+        let mut payload = json!({
+            "channel": self.channel,
+            "text": text,
+            "unfurl_links": false,
+        });
+
+        if let Some(thread_ts) = thread_ts {
+            payload["thread_ts"] = json!(thread_ts);
+        }
+
+        payload
+    }
+
+    pub fn parent_text(date: NaiveDate) -> String {
+        format!("*New videos - {}*", date.format("%Y-%m-%d"))
+    }
+
+    // Reuses today's parent message or posts a new one
+    pub async fn thread(
+        &self,
+        current: Option<&SlackThread>,
+        now: DateTime<Utc>,
+        host: Option<&str>,
+    ) -> Result<SlackThread> {
+        let today = now.date_naive();
+
+        if let Some(thread) = current {
+            if thread.date == today {
+                return Ok(thread.clone());
+            }
+        }
+
+        let payload = self.message_payload(&Self::parent_text(today), None);
+        let ts = self.post_message(&payload, host).await?;
+
+        Ok(SlackThread { date: today, ts })
+    }
+
+    pub async fn post_message(&self, payload: &Value, host: Option<&str>) -> Result<String> {
+        let host = host.unwrap_or(SLACK_API_HOST);
+        let client = Client::new();
+
+        let res = client
+            .post(format!("{}/api/chat.postMessage", host))
+            .bearer_auth(&self.bot_token)
+            .json(payload)
+            .send()
+            .await?;
+
+        if res.status() != 200 {
+            let err_msg = res.text().await?;
+            eyre::bail!("Failed to send message to Slack: {err_msg}");
+        }
+
+        // Slack API responds with 200 and an "ok" flag
+        let res_json: Value = res.json().await?;
+        if res_json["ok"] != json!(true) {
+            eyre::bail!(
+                "Failed to send message to Slack: {}",
+                res_json["error"].as_str().unwrap_or("unknown error")
+            );
+        }
+
+        match res_json["ts"].as_str() {
+            Some(ts) => Ok(ts.to_string()),
+            None => eyre::bail!("Missing message timestamp in Slack response"),
+        }
+    }
+}
+
+#[async_trait]
+impl Notify for SlackAppConfig {
+    fn name(&self) -> &str {
+        "SlackApp"
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.bot_token.is_empty() || self.channel.is_empty() {
+            eyre::bail!("Slack app notifier requires bot_token and channel")
+        }
+        Ok(())
+    }
+
+    fn render(&self, video: &Video, templates: &Templates) -> String {
+        templates
+            .render_video(video)
+            .unwrap_or_else(|| video.mrkdwn_text())
+    }
+
+    async fn send(&self, messages: Vec<String>, _cron: bool) -> Result<()> {
+        let payload = self.message_payload(&messages.join("\n\n"), None);
+        self.post_message(&payload, None).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::{Matcher, Server};
+
+    use super::*;
+
+    fn build_config() -> SlackAppConfig {
+        SlackAppConfig {
+            bot_token: "xoxb-test".to_string(),
+            channel: "C123".to_string(),
+            schedule: None,
+            blocks: false,
+            templates: Templates::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_thread_reused_on_same_day() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+        let m = server
+            .mock("POST", "/api/chat.postMessage")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let now: DateTime<Utc> = "2024-11-07T08:00:00Z".parse()?;
+        let current = SlackThread {
+            date: now.date_naive(),
+            ts: "1730966400.000100".to_string(),
+        };
+
+        let thread = build_config()
+            .thread(Some(&current), now, Some(&host))
+            .await?;
+        assert_eq!(thread, current);
+
+        m.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_new_thread_posted() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+        let m = server
+            .mock("POST", "/api/chat.postMessage")
+            .match_header("authorization", "Bearer xoxb-test")
+            .match_body(Matcher::PartialJson(json!({
+                "channel": "C123",
+                "text": "*New videos - 2024-11-08*",
+            })))
+            .with_body(r#"{"ok": true, "ts": "1731052800.000200"}"#)
+            .create_async()
+            .await;
+
+        let now: DateTime<Utc> = "2024-11-08T08:00:00Z".parse()?;
+        let previous = SlackThread {
+            date: "2024-11-07".parse()?,
+            ts: "1730966400.000100".to_string(),
+        };
+
+        let thread = build_config()
+            .thread(Some(&previous), now, Some(&host))
+            .await?;
+        assert_eq!(thread.ts, "1731052800.000200");
+        assert_eq!(thread.date, now.date_naive());
+
+        m.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_post_message_error() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let host = format!("http://{}", server.host_with_port());
+        let m = server
+            .mock("POST", "/api/chat.postMessage")
+            .with_body(r#"{"ok": false, "error": "invalid_auth"}"#)
+            .create_async()
+            .await;
+
+        let payload = build_config().message_payload("test", None);
+        let err = build_config()
+            .post_message(&payload, Some(&host))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid_auth"));
+
+        m.assert_async().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use chrono::Utc;
+use eyre::Result;
+use minijinja::{context, Environment, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::{channel::Channel, video::Video};
+
+// Templates use the minijinja (Jinja2) syntax, e.g. "{{ channel.name }}: {{ video.title }}"
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct Templates {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+}
+
+impl Templates {
+    pub fn is_empty(&self) -> bool {
+        self.video.is_none() && self.header.is_none() && self.footer.is_none()
+    }
+
+    pub fn or(&self, defaults: &Templates) -> Templates {
+        Templates {
+            video: self.video.clone().or(defaults.video.clone()),
+            header: self.header.clone().or(defaults.header.clone()),
+            footer: self.footer.clone().or(defaults.footer.clone()),
+        }
+    }
+
+    // Renders templates with sample data, so errors surface when the config is loaded
+    pub fn validate(&self) -> Result<()> {
+        let sample = Video {
+            channel: "Channel".to_string(),
+            title: "Title".to_string(),
+            link: "https://www.youtube.com/watch?v=VIDEO_ID".to_string(),
+            published_at: Utc::now(),
+            thumbnail_url: None,
+            channel_handle: "@handle".to_string(),
+            channel_id: "CHANNEL_ID".to_string(),
+        };
+
+        if let Some(template) = &self.video {
+            render(template, video_context(&sample))
+                .map_err(|e| eyre::eyre!("Invalid video template: {e}"))?;
+        }
+
+        let videos = [sample];
+        if let Some(template) = &self.header {
+            render(template, run_context(&videos))
+                .map_err(|e| eyre::eyre!("Invalid header template: {e}"))?;
+        }
+
+        if let Some(template) = &self.footer {
+            render(template, run_context(&videos))
+                .map_err(|e| eyre::eyre!("Invalid footer template: {e}"))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn render_video(&self, video: &Video) -> Option<String> {
+        let template = self.video.as_ref()?;
+        render_or_log("video", template, video_context(video))
+    }
+
+    pub fn render_header(&self, videos: &[Video]) -> Option<String> {
+        let template = self.header.as_ref()?;
+        render_or_log("header", template, run_context(videos))
+    }
+
+    pub fn render_footer(&self, videos: &[Video]) -> Option<String> {
+        let template = self.footer.as_ref()?;
+        render_or_log("footer", template, run_context(videos))
+    }
+}
+
+fn render(template: &str, ctx: Value) -> Result<String> {
+    let env = Environment::new();
+    Ok(env.render_str(template, ctx)?)
+}
+
+// Templates are validated on load, but can still fail on real data, e.g. a filter
+// applied to a missing field. The default format is used instead.
+fn render_or_log(kind: &str, template: &str, ctx: Value) -> Option<String> {
+    match render(template, ctx) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            log::error!("Failed to render {kind} template, using the default format: {e}");
+            None
+        }
+    }
+}
+
+fn video_context(video: &Video) -> Value {
+    let channel = Channel {
+        handle: video.channel_handle.clone(),
+        description: video.channel.clone(),
+        channel_id: video.channel_id.clone(),
+    };
+
+    context! {
+        video => video,
+        channel => context! {
+            name => video.channel,
+            handle => channel.handle,
+            channel_id => channel.channel_id,
+            url => channel.url(),
+            rss_url => channel.rss_url(),
+        },
+    }
+}
+
+fn run_context(videos: &[Video]) -> Value {
+    context! {
+        videos => videos,
+        count => videos.len(),
+        date => Utc::now().format("%Y-%m-%d").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_video() -> Video {
+        Video {
+            channel: "Man of Recaps".to_string(),
+            title: "Rings of Power RECAP: Season 2".to_string(),
+            link: "https://www.youtube.com/watch?v=CjeUx_HHtF0".to_string(),
+            published_at: Utc::now(),
+            thumbnail_url: None,
+            channel_handle: "@ManofRecaps".to_string(),
+            channel_id: "UCNCTxLZ3EKKry-oWgLlsYsw".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_templates() {
+        let templates = Templates {
+            video: Some("{{ channel.handle }}: {{ video.title }} ({{ channel.url }})".to_string()),
+            header: Some("{{ count }} new videos".to_string()),
+            footer: None,
+        };
+        templates.validate().unwrap();
+
+        let video = build_video();
+        assert_eq!(
+            templates.render_video(&video).unwrap(),
+            "@ManofRecaps: Rings of Power RECAP: Season 2 (https://www.youtube.com/@ManofRecaps)"
+        );
+        assert_eq!(templates.render_header(&[video]).unwrap(), "1 new videos");
+        assert!(templates.render_footer(&[]).is_none());
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        let templates = Templates {
+            video: Some("{{ video.title ".to_string()),
+            ..Default::default()
+        };
+        let err = templates.validate().unwrap_err();
+        assert!(err.to_string().contains("Invalid video template"));
+
+        let templates = Templates {
+            footer: Some("{% for video in videos %}".to_string()),
+            ..Default::default()
+        };
+        assert!(templates.validate().is_err());
+    }
+
+    #[test]
+    fn test_templates_fallback() {
+        let defaults = Templates {
+            video: Some("default".to_string()),
+            header: Some("header".to_string()),
+            footer: None,
+        };
+        let templates = Templates {
+            video: Some("custom".to_string()),
+            ..Default::default()
+        };
+
+        let merged = templates.or(&defaults);
+        assert_eq!(merged.video, Some("custom".to_string()));
+        assert_eq!(merged.header, Some("header".to_string()));
+        assert!(merged.footer.is_none());
+    }
+}
@@ -1,10 +1,12 @@
+use chrono::{DateTime, Duration, Utc};
+use eyre::Result;
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Display, Formatter},
     path::PathBuf,
 };
 
-use crate::{channel::Channel, notifier::Notifier};
+use crate::{channel::Channel, notifier::Notifier, schedule::Schedule, templates::Templates};
 
 pub const API_HOST: &str = "https://ytsub.apki.io";
 
@@ -15,7 +17,10 @@ pub struct UserSettings {
     pub api_key: Option<String>,
     #[serde(skip_serializing, skip_deserializing)]
     pub path: PathBuf,
-    pub schedule: Option<Vec<u32>>,
+    pub schedule: Option<Schedule>,
+    // Defaults for all notifiers, overridden by notifier specific templates
+    #[serde(default, skip_serializing_if = "Templates::is_empty")]
+    pub templates: Templates,
 }
 
 impl Display for UserSettings {
@@ -33,6 +38,7 @@ impl UserSettings {
             channels: vec![],
             api_key: None,
             schedule: None,
+            templates: Templates::default(),
         }
     }
 
@@ -53,12 +59,136 @@ impl UserSettings {
     pub fn get_slack_notifier(&self) -> Option<&Notifier> {
         self.notifiers.iter().find(|n| n.is_slack())
     }
+
+    pub fn validate_schedule(&self) -> Result<()> {
+        if let Some(schedule) = &self.schedule {
+            schedule.validate()?;
+        }
+
+        for notifier in &self.notifiers {
+            if let Some(schedule) = notifier.schedule() {
+                schedule.validate()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_notifiers(&self) -> Result<()> {
+        for (index, notifier) in self.notifiers.iter().enumerate() {
+            notifier
+                .validate()
+                .map_err(|e| eyre::eyre!("Invalid notifier {index} ({}): {e}", notifier.name()))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_templates(&self) -> Result<()> {
+        self.templates.validate()?;
+
+        for notifier in &self.notifiers {
+            if let Some(templates) = notifier.templates() {
+                templates.validate()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn notifier_templates(&self, notifier: &Notifier) -> Templates {
+        match notifier.templates() {
+            Some(templates) => templates.or(&self.templates),
+            None => self.templates.clone(),
+        }
+    }
+
+    // Time of the next notification, `now` if one is already due
+    pub fn next_due_at(
+        &self,
+        last_run_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let Some(schedule) = &self.schedule else {
+            return Some(now);
+        };
+
+        let since = last_run_at.unwrap_or(now - Duration::hours(1));
+        schedule.next_after(since).map(|slot| slot.max(now))
+    }
+
+    pub fn diff(&self, remote: &UserSettings) -> SettingsDiff {
+        let missing = |from: &UserSettings, other: &UserSettings| {
+            from.channels
+                .iter()
+                .filter(|c| other.get_channel_by_id(&c.channel_id).is_none())
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        SettingsDiff {
+            local_channels: missing(self, remote),
+            remote_channels: missing(remote, self),
+            notifiers: self.notifiers != remote.notifiers,
+            schedule: self.schedule != remote.schedule,
+            templates: self.templates != remote.templates,
+        }
+    }
+
+    // Without a previous run only slots from the last hour are considered
+    pub fn schedule_due(&self, last_run_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+        let Some(schedule) = &self.schedule else {
+            return true;
+        };
+
+        let since = last_run_at.unwrap_or(now - Duration::hours(1));
+        schedule.is_due(since, now)
+    }
+}
+
+// Differences between the local config and remote settings
+#[derive(Debug, PartialEq, Default, Serialize)]
+pub struct SettingsDiff {
+    pub local_channels: Vec<Channel>,
+    pub remote_channels: Vec<Channel>,
+    pub notifiers: bool,
+    pub schedule: bool,
+    pub templates: bool,
+}
+
+impl SettingsDiff {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl Display for SettingsDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut lines = vec![];
+
+        for channel in &self.local_channels {
+            lines.push(format!("+ {} (only in local config)", channel.handle));
+        }
+        for channel in &self.remote_channels {
+            lines.push(format!("- {} (only in remote settings)", channel.handle));
+        }
+        for (changed, name) in [
+            (self.notifiers, "notifiers"),
+            (self.schedule, "schedule"),
+            (self.templates, "templates"),
+        ] {
+            if changed {
+                lines.push(format!("~ {name} differ"));
+            }
+        }
+
+        write!(f, "{}", lines.join("\n"))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use eyre::Result;
     #[tokio::test]
     async fn test_json_serialize() -> Result<()> {
         let setting = UserSettings::default(PathBuf::from("test.toml"));
@@ -68,4 +198,64 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_schedule_due() -> Result<()> {
+        let mut setting = UserSettings::default(PathBuf::from("test.toml"));
+        let now: DateTime<Utc> = "2024-11-07T08:30:00Z".parse()?;
+
+        assert!(setting.schedule_due(None, now));
+
+        setting.schedule = Some(Schedule::Hours(vec![8, 20]));
+        assert!(setting.schedule_due(None, now));
+        assert!(!setting.schedule_due(Some(now - Duration::minutes(10)), now));
+
+        setting.schedule = Some(Schedule::Hours(vec![9, 20]));
+        assert!(!setting.schedule_due(None, now));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_settings_diff() {
+        let channel = |id: &str| Channel {
+            handle: format!("@{id}"),
+            description: id.to_string(),
+            channel_id: id.to_string(),
+        };
+
+        let mut local = UserSettings::default(PathBuf::from("test.toml"));
+        let mut remote = UserSettings::default(PathBuf::from("test.toml"));
+        assert!(local.diff(&remote).is_empty());
+
+        local.channels = vec![channel("A"), channel("B")];
+        remote.channels = vec![channel("B"), channel("C")];
+        remote.schedule = Some(Schedule::Hours(vec![8]));
+
+        let diff = local.diff(&remote);
+        assert_eq!(diff.local_channels, vec![channel("A")]);
+        assert_eq!(diff.remote_channels, vec![channel("C")]);
+        assert!(diff.schedule);
+        assert!(!diff.notifiers);
+        assert_eq!(
+            diff.to_string(),
+            "+ @A (only in local config)\n- @C (only in remote settings)\n~ schedule differ"
+        );
+    }
+
+    #[test]
+    fn test_next_due_at() -> Result<()> {
+        let mut setting = UserSettings::default(PathBuf::from("test.toml"));
+        let now: DateTime<Utc> = "2024-11-07T08:30:00Z".parse()?;
+        assert_eq!(setting.next_due_at(None, now), Some(now));
+
+        setting.schedule = Some(Schedule::Hours(vec![8, 20]));
+        assert_eq!(setting.next_due_at(None, now), Some(now));
+        assert_eq!(
+            setting.next_due_at(Some(now), now),
+            Some("2024-11-07T20:00:00Z".parse()?)
+        );
+
+        Ok(())
+    }
 }
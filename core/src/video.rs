@@ -1,15 +1,22 @@
 use chrono::{DateTime, Utc};
 use eyre::Result;
+use serde::{Deserialize, Serialize};
 use xmltojson::to_json;
 
-use crate::notifier::Notifier;
+use crate::{notifier::Notifier, templates::Templates};
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct Video {
     pub channel: String,
     pub title: String,
     pub link: String,
     pub published_at: DateTime<Utc>,
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+    #[serde(default)]
+    pub channel_handle: String,
+    #[serde(default)]
+    pub channel_id: String,
 }
 
 impl Video {
@@ -25,12 +32,19 @@ impl Video {
             let published_at: DateTime<Utc> =
                 published_at.parse().expect("Failed to parse DateTime");
             let link = video_data["link"]["@href"].as_str().unwrap();
+            let channel_id = video_data["yt:channelId"].as_str().unwrap_or_default();
+            let thumbnail_url = video_data["media:group"]["media:thumbnail"]["@url"]
+                .as_str()
+                .map(|url| url.to_string());
 
             let video = Video {
                 channel: channel.to_string(),
                 title: title.to_string(),
                 link: link.to_string(),
                 published_at,
+                thumbnail_url,
+                channel_handle: String::new(),
+                channel_id: channel_id.to_string(),
             };
 
             videos.push(video);
@@ -39,22 +53,25 @@ impl Video {
         Ok(videos)
     }
 
-    pub fn notification_text(&self, notifier: &Notifier) -> String {
-        match notifier {
-            Notifier::Log() => {
-                format!("New video - {} {} {}", self.channel, self.title, self.link)
-            }
-            Notifier::Slack(_) => {
-                format!(
-                    "*New video - {}* <{}|{}>",
-                    self.channel, self.link, self.title
-                )
-            }
-            Notifier::Telegram => {
-                todo!()
-            }
+    pub fn notification_text(&self, notifier: &Notifier, templates: &Templates) -> String {
+        match notifier.backend() {
+            Ok(backend) => backend.render(self, templates),
+            Err(_) => templates
+                .render_video(self)
+                .unwrap_or_else(|| self.plain_text()),
         }
     }
+
+    pub fn plain_text(&self) -> String {
+        format!("New video - {} {} {}", self.channel, self.title, self.link)
+    }
+
+    pub fn mrkdwn_text(&self) -> String {
+        format!(
+            "*New video - {}* <{}|{}>",
+            self.channel, self.link, self.title
+        )
+    }
 }
 
 #[cfg(test)]
@@ -67,5 +84,10 @@ mod tests {
         let rss_data = fs::read_to_string("src/fixtures/yt_videos_data.xml").unwrap();
         let videos = Video::parse_rss(rss_data).unwrap();
         assert_eq!(videos.len(), 15);
+        assert_eq!(
+            videos[0].thumbnail_url,
+            Some("https://i4.ytimg.com/vi/3lBGUjXhFs0/hqdefault.jpg".to_string())
+        );
+        assert_eq!(videos[0].channel_id, "UCNCTxLZ3EKKry-oWgLlsYsw");
     }
 }